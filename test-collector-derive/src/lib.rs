@@ -4,7 +4,6 @@
 //! use test_collector_derive::collect_test;
 //!
 //!     #[collect_test]
-//!     #[test]
 //!     pub fn sync_test_success() {
 //!         println!("Executed sync!");
 //!         assert_eq!(true, true);
@@ -20,14 +19,81 @@
 //!         assert!(response.is_ok());
 //!     }
 //!
+//!     // the runner stops waiting for the test after 30 seconds and reports it as timed out,
+//!     // supported units are ms, s, m and h
+//!     #[collect_test(async, timeout = "30s")]
+//!     pub async fn async_test_with_timeout() {
+//!     }
+//!
+//!     // not executed unless the runner is asked for ignored tests, e.g. with `--include-ignored`
+//!     #[collect_test(ignore = "payment provider sandbox is down")]
+//!     pub fn ignored_test() {
+//!     }
+//!
+//!     // selected by tag expressions, e.g. `-- --tags "smoke and not external"`
+//!     #[collect_test(tags("smoke", "db"))]
+//!     pub fn user_is_stored() {
+//!     }
+//!
+//!     // executed up to 4 times, reported as flaky if it passes only after a retry
+//!     #[collect_test(async, retries = 3)]
+//!     pub async fn calls_external_service() {
+//!     }
+//!
+//!     // gets the environment and the fixtures it provides with `TestEnvironment::fixture` from the runner,
+//!     // sync tests taking fixtures are executed on the thread which owns the environment and can not have a timeout
+//!     # pub struct MyEnv { base_url: String }
+//!     # pub struct PgPool;
+//!     #[collect_test(async)]
+//!     pub async fn user_is_created(env: &MyEnv, pool: &PgPool) {
+//!         println!("Calling {}", env.base_url);
+//!     }
+//!
+//!     // executed together with the other tests of the suite, between the start and stop
+//!     // of the suite registered with `TestRunner::with_suite("billing", ...)`
+//!     #[collect_test(suite = "billing")]
+//!     pub fn invoice_is_sent() {
+//!     }
+//!
+//!     // executed after the tests with a lower order and after `create_account` passed,
+//!     // skipped if `create_account` failed
+//!     # #[collect_test(order = 1)]
+//!     # pub fn create_account() {
+//!     # }
+//!     #[collect_test(order = 2, depends_on = "create_account")]
+//!     pub fn place_order() {
+//!     }
+//!
+//!     // never executed at the same time as the other tests of the `kafka` group,
+//!     // `#[collect_test(serial)]` is never executed at the same time as any other test
+//!     #[collect_test(serial = "kafka")]
+//!     pub fn order_event_is_published() {
+//!     }
+//!
+//!     // every case is a separate test, `check_endpoint::case_1` and `check_endpoint::case_2`,
+//!     // the arguments of the case go first, the fixtures after them
+//!     #[collect_test(async)]
+//!     #[case("/health", 200)]
+//!     #[case("/missing", 404)]
+//!     pub async fn check_endpoint(path: &str, status: u16) {
+//!         println!("GET {} must return {}", path, status);
+//!     }
+//!
+//!     // passes only if it panics with a message containing the expected string
+//!     #[collect_test(should_panic(expected = "insufficient funds"))]
+//!     pub fn overdraft_is_rejected() {
+//!         panic!("insufficient funds on account 42");
+//!     }
+//!
 //! ```
 extern crate core;
 
 use proc_macro::{TokenStream};
 use proc_macro2::Span;
 
-use syn::{AttributeArgs, Ident, ItemFn, Lit, Meta, NestedMeta, parse_macro_input, Path};
-use quote::{quote, TokenStreamExt, ToTokens};
+use syn::{Attribute, AttributeArgs, Expr, FnArg, Ident, ItemFn, Lit, Meta, NestedMeta, parse_macro_input, Path, PatType, Token, Type, TypeReference};
+use syn::punctuated::Punctuated;
+use quote::{quote, quote_spanned, TokenStreamExt};
 use rand::{thread_rng, Rng};
 use rand::distributions::Alphanumeric;
 use syn::spanned::Spanned;
@@ -35,8 +101,9 @@ use syn::spanned::Spanned;
 #[proc_macro_attribute]
 pub fn collect_test(args: TokenStream, input: TokenStream) -> TokenStream {
     // Read the user test
-    let is_async = is_async(parse_macro_input!(args as AttributeArgs));
-    let fn_user_test = parse_macro_input!(input as ItemFn);
+    let attributes = TestAttributes::parse(parse_macro_input!(args as AttributeArgs));
+    let mut fn_user_test = parse_macro_input!(input as ItemFn);
+    let cases = take_cases(&mut fn_user_test);
 
     // Add some random to the generated function names so
     // we can support using the macro multiple times in the same file
@@ -54,14 +121,47 @@ pub fn collect_test(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let test_body = &fn_user_test.block;
 
+    // Spanned to the test function, so line!() and column!() point to it instead of the attribute
+    let mut meta_modifiers = quote_spanned! {fn_user_test.sig.ident.span()=>
+        .with_location(module_path!(), file!(), line!(), column!())
+    };
+    meta_modifiers.append_all(attributes.meta_modifiers());
+
+    let case_arguments = cases.first().map_or(0, Vec::len);
+    if cases.iter().any(|case| case.len() != case_arguments) {
+        panic!("All cases of [{}] must have the same number of arguments", test_name);
+    }
+    let fixture_types = fixture_types(&fn_user_test, case_arguments);
+    if !fixture_types.is_empty() && !attributes.is_async && attributes.timeout_millis.is_some() {
+        panic!("Sync test [{}] takes fixtures and can not be abandoned, so it can not have a timeout", test_name);
+    }
+    let user_test_iden = &fn_user_test.sig.ident;
+
     //Creating another function, because I was not able to find how to put exact same function to struct
-    let output_fn = if is_async {
+    let output_fn = if !cases.is_empty() {
+        // Every case is a separate test named `test::case_N`, its arguments go first, then the fixtures
+        let mut output_fn = proc_macro2::TokenStream::new();
+        for (index, case) in cases.iter().enumerate() {
+            let case_name = format!("{}::case_{}", test_name, index + 1);
+            let case_iden = Ident::new(&format!("{}_case_{}", wrapper_test_name, index + 1), Span::call_site());
+            let description = case.iter().map(|argument| quote!(#argument).to_string()).collect::<Vec<_>>().join(", ");
+            let modifiers = quote! { #meta_modifiers.with_case(#description) };
+            let arguments = quote! { #(#case,)* #(fixtures.get::<#fixture_types>()),* };
+            output_fn.append_all(call_wrapper(&case_name, &case_iden, user_test_iden, arguments,
+                                              !fixture_types.is_empty(), attributes.is_async, &modifiers));
+        }
+        output_fn
+    } else if !fixture_types.is_empty() {
+        // Tests taking fixtures call the original function with the references provided by the runner
+        let arguments = quote! { #(fixtures.get::<#fixture_types>()),* };
+        call_wrapper(&test_name, &wrapped_test_iden, user_test_iden, arguments, true, attributes.is_async, &meta_modifiers)
+    } else if attributes.is_async {
         quote! {
             inventory::submit!{
                 test_collector_utils::IntegrationTestMeta::for_async_fn(
                   #test_name.to_string(),
                   Box::new(|| Box::pin(#wrapped_test_iden()))
-                )
+                )#meta_modifiers
             }
             pub async fn #wrapped_test_iden() {
                 #test_body
@@ -73,7 +173,7 @@ pub fn collect_test(args: TokenStream, input: TokenStream) -> TokenStream {
                 test_collector_utils::IntegrationTestMeta::for_sync_fn(
                   #test_name.to_string(),
                   #wrapped_test_iden
-                )
+                )#meta_modifiers
             }
 
             pub fn #wrapped_test_iden() {
@@ -82,40 +182,239 @@ pub fn collect_test(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
-    let mut fn_user_test = fn_user_test.to_token_stream();
+    // The original function is only called directly when it is also marked with #[test],
+    // otherwise the runner uses the wrapper above, so do not warn about it
+    let mut fn_user_test = quote! {
+        #[allow(dead_code)]
+        #fn_user_test
+    };
 
     fn_user_test.append_all(output_fn);
 
     fn_user_test.into()
 }
 
-fn is_async(args: AttributeArgs) -> bool {
-    let mut is_async = false;
-    for attr in args {
-        if is_async {
-            break;
-        }
-        match attr {
-            NestedMeta::Meta(meta) => {
-                is_async = from_meta(meta);
+/// Registers a wrapper which calls the original test function with the given arguments,
+/// they can use `fixtures` if the test takes fixtures
+fn call_wrapper(name: &str,
+                wrapper: &Ident,
+                user_test: &Ident,
+                arguments: proc_macro2::TokenStream,
+                with_fixtures: bool,
+                is_async: bool,
+                meta_modifiers: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match (with_fixtures, is_async) {
+        (true, true) => quote! {
+            inventory::submit!{
+                test_collector_utils::IntegrationTestMeta::for_async_fixture_fn(
+                  #name.to_string(),
+                  #wrapper
+                )#meta_modifiers
             }
-            _ => panic!("invalid syntax {:?}", attr.span()),
-        }
+            pub fn #wrapper<'a>(fixtures: &'a dyn test_collector_utils::Fixtures)
+                -> std::pin::Pin<Box<dyn std::future::Future<Output=()> + 'a>> {
+                Box::pin(#user_test(#arguments))
+            }
+        },
+        (true, false) => quote! {
+            inventory::submit!{
+                test_collector_utils::IntegrationTestMeta::for_fixture_fn(
+                  #name.to_string(),
+                  #wrapper
+                )#meta_modifiers
+            }
+            pub fn #wrapper(fixtures: &dyn test_collector_utils::Fixtures) {
+                #user_test(#arguments)
+            }
+        },
+        (false, true) => quote! {
+            inventory::submit!{
+                test_collector_utils::IntegrationTestMeta::for_async_fn(
+                  #name.to_string(),
+                  Box::new(#wrapper)
+                )#meta_modifiers
+            }
+            pub fn #wrapper() -> std::pin::Pin<Box<dyn std::future::Future<Output=()>>> {
+                Box::pin(#user_test(#arguments))
+            }
+        },
+        (false, false) => quote! {
+            inventory::submit!{
+                test_collector_utils::IntegrationTestMeta::for_sync_fn(
+                  #name.to_string(),
+                  #wrapper
+                )#meta_modifiers
+            }
+            pub fn #wrapper() {
+                #user_test(#arguments)
+            }
+        },
     }
-    return is_async;
 }
 
-fn from_meta(meta: Meta) -> bool {
-    match meta {
-        Meta::NameValue(name_value) => match get_key(&name_value.path).as_str() {
-            "async" => async_nv(&name_value),
-            _ => panic!("Unsupported key {:?}", name_value.span()),
-        },
-        Meta::Path(path) => match get_key(&path).as_str() {
-            "async" => true,
-            _ => panic!("Unsupported key {:?}", path.span()),
-        },
-        _ => panic!("Unsupported attribute: {:?}", meta.span()),
+/// Removes the `#[case(...)]` attributes of the test and returns their arguments
+fn take_cases(fn_user_test: &mut ItemFn) -> Vec<Vec<Expr>> {
+    let (cases, attrs): (Vec<Attribute>, Vec<Attribute>) = fn_user_test.attrs.drain(..)
+        .partition(|attr| attr.path.is_ident("case"));
+    fn_user_test.attrs = attrs;
+    cases.iter()
+        .map(|attr| attr.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+            .unwrap_or_else(|e| panic!("Invalid case arguments: {} {:?}", e, attr.span()))
+            .into_iter()
+            .collect())
+        .collect()
+}
+
+/// Types of the fixtures the test takes, the arguments after the ones of the cases
+/// must be shared references, e.g. `env: &MyEnv`
+fn fixture_types(fn_user_test: &ItemFn, case_arguments: usize) -> Vec<Type> {
+    fn_user_test.sig.inputs.iter()
+        .skip(case_arguments)
+        .map(|input| match input {
+            FnArg::Typed(PatType { ty, .. }) => match ty.as_ref() {
+                Type::Reference(TypeReference { mutability: None, elem, .. }) => elem.as_ref().clone(),
+                _ => panic!("Test arguments must be shared references to the environment or its fixtures {:?}", ty.span()),
+            },
+            FnArg::Receiver(receiver) => panic!("Tests can not take self {:?}", receiver.span()),
+        })
+        .collect()
+}
+
+/// Arguments of the `#[collect_test(...)]` attribute
+#[derive(Default)]
+struct TestAttributes {
+    is_async: bool,
+    timeout_millis: Option<u64>,
+    ignored: bool,
+    ignore_reason: Option<String>,
+    should_panic: bool,
+    expected_panic: Option<String>,
+    tags: Vec<String>,
+    retries: Option<u32>,
+    suite: Option<String>,
+    order: Option<i32>,
+    depends_on: Vec<String>,
+    serial: bool,
+    serial_group: Option<String>,
+}
+
+impl TestAttributes {
+    fn parse(args: AttributeArgs) -> TestAttributes {
+        let mut attributes = TestAttributes::default();
+        for attr in args {
+            match attr {
+                NestedMeta::Meta(meta) => attributes.apply(meta),
+                _ => panic!("invalid syntax {:?}", attr.span()),
+            }
+        }
+        attributes
+    }
+
+    fn apply(&mut self, meta: Meta) {
+        match meta {
+            Meta::NameValue(name_value) => match get_key(&name_value.path).as_str() {
+                "async" => self.is_async = async_nv(&name_value),
+                "timeout" => self.timeout_millis = Some(timeout_nv(&name_value)),
+                "retries" => self.retries = Some(retries_nv(&name_value)),
+                "suite" => self.suite = Some(str_nv(&name_value, "Suite")),
+                "order" => self.order = Some(order_nv(&name_value)),
+                "depends_on" => self.depends_on.push(str_nv(&name_value, "Dependency")),
+                "serial" => {
+                    self.serial = true;
+                    self.serial_group = Some(str_nv(&name_value, "Serial group"));
+                }
+                "ignore" => {
+                    self.ignored = true;
+                    self.ignore_reason = Some(str_nv(&name_value, "Ignore reason"));
+                }
+                "should_panic" => {
+                    self.should_panic = true;
+                    self.expected_panic = Some(str_nv(&name_value, "Expected panic message"));
+                }
+                _ => panic!("Unsupported key {:?}", name_value.span()),
+            },
+            Meta::Path(path) => match get_key(&path).as_str() {
+                "async" => self.is_async = true,
+                "ignore" => self.ignored = true,
+                "should_panic" => self.should_panic = true,
+                "serial" => self.serial = true,
+                _ => panic!("Unsupported key {:?}", path.span()),
+            },
+            Meta::List(list) => match get_key(&list.path).as_str() {
+                "should_panic" => {
+                    self.should_panic = true;
+                    self.expected_panic = expected_panic_list(&list);
+                }
+                "tags" => self.tags.extend(tags_list(&list)),
+                "depends_on" => self.depends_on.extend(depends_on_list(&list)),
+                _ => panic!("Unsupported key {:?}", list.span()),
+            },
+        }
+    }
+
+    /// Builder calls which are appended to the created IntegrationTestMeta
+    fn meta_modifiers(&self) -> proc_macro2::TokenStream {
+        let mut modifiers = proc_macro2::TokenStream::new();
+        if let Some(timeout_millis) = self.timeout_millis {
+            modifiers.append_all(quote! {
+                .with_timeout(std::time::Duration::from_millis(#timeout_millis))
+            });
+        }
+        if self.ignored {
+            let reason = match &self.ignore_reason {
+                Some(reason) => quote! { Some(#reason) },
+                None => quote! { None },
+            };
+            modifiers.append_all(quote! {
+                .ignored(#reason)
+            });
+        }
+        if self.should_panic {
+            let expected = match &self.expected_panic {
+                Some(expected) => quote! { Some(#expected) },
+                None => quote! { None },
+            };
+            modifiers.append_all(quote! {
+                .should_panic(#expected)
+            });
+        }
+        if let Some(retries) = self.retries {
+            modifiers.append_all(quote! {
+                .with_retries(#retries)
+            });
+        }
+        if let Some(suite) = &self.suite {
+            modifiers.append_all(quote! {
+                .in_suite(#suite)
+            });
+        }
+        if self.serial {
+            let group = match &self.serial_group {
+                Some(group) => quote! { Some(#group) },
+                None => quote! { None },
+            };
+            modifiers.append_all(quote! {
+                .serial(#group)
+            });
+        }
+        if let Some(order) = self.order {
+            modifiers.append_all(quote! {
+                .with_order(#order)
+            });
+        }
+        if !self.depends_on.is_empty() {
+            let depends_on = &self.depends_on;
+            modifiers.append_all(quote! {
+                .depends_on(&[#(#depends_on),*])
+            });
+        }
+        if !self.tags.is_empty() {
+            let tags = &self.tags;
+            modifiers.append_all(quote! {
+                .with_tags(&[#(#tags),*])
+            });
+        }
+        modifiers
     }
 }
 
@@ -127,6 +426,86 @@ fn async_nv(name_value: &syn::MetaNameValue) -> bool {
     }
 }
 
+fn str_nv(name_value: &syn::MetaNameValue, what: &str) -> String {
+    if let Lit::Str(str_lit) = &name_value.lit {
+        str_lit.value()
+    } else {
+        panic!("{} must be LitStr {:?}", what, name_value.span())
+    }
+}
+
+/// Parses `should_panic(expected = "message")`
+fn expected_panic_list(list: &syn::MetaList) -> Option<String> {
+    let mut expected = None;
+    for nested in &list.nested {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(name_value)) if get_key(&name_value.path) == "expected" => {
+                expected = Some(str_nv(name_value, "Expected panic message"));
+            }
+            _ => panic!("Only `expected = \"message\"` is supported by should_panic {:?}", nested.span()),
+        }
+    }
+    expected
+}
+
+/// Parses `tags("smoke", "db")`
+fn tags_list(list: &syn::MetaList) -> Vec<String> {
+    list.nested.iter()
+        .map(|nested| match nested {
+            NestedMeta::Lit(Lit::Str(tag)) => tag.value(),
+            _ => panic!("Tags must be LitStr, e.g. tags(\"smoke\", \"db\") {:?}", nested.span()),
+        })
+        .collect()
+}
+
+/// Parses `depends_on("create_account", "place_order")`
+fn depends_on_list(list: &syn::MetaList) -> Vec<String> {
+    list.nested.iter()
+        .map(|nested| match nested {
+            NestedMeta::Lit(Lit::Str(test)) => test.value(),
+            _ => panic!("Dependencies must be LitStr, e.g. depends_on(\"create_account\") {:?}", nested.span()),
+        })
+        .collect()
+}
+
+fn order_nv(name_value: &syn::MetaNameValue) -> i32 {
+    if let Lit::Int(int_lit) = &name_value.lit {
+        int_lit.base10_parse()
+            .unwrap_or_else(|e| panic!("Order must be a number: {} {:?}", e, name_value.span()))
+    } else {
+        panic!("Order must be LitInt {:?}", name_value.span())
+    }
+}
+
+fn retries_nv(name_value: &syn::MetaNameValue) -> u32 {
+    if let Lit::Int(int_lit) = &name_value.lit {
+        int_lit.base10_parse()
+            .unwrap_or_else(|e| panic!("Retries must be a positive number: {} {:?}", e, name_value.span()))
+    } else {
+        panic!("Retries must be LitInt {:?}", name_value.span())
+    }
+}
+
+fn timeout_nv(name_value: &syn::MetaNameValue) -> u64 {
+    parse_duration_millis(&str_nv(name_value, "Timeout"))
+        .unwrap_or_else(|| panic!("Timeout must be a number with ms, s, m or h unit, e.g. \"30s\" {:?}", name_value.span()))
+}
+
+fn parse_duration_millis(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let unit_start = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().ok()?;
+    let multiplier = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
+    };
+    amount.checked_mul(multiplier)
+}
+
 fn get_key(p: &Path) -> String {
     let mut key: Vec<String> = p
         .segments
@@ -0,0 +1,240 @@
+//! Parsing of the command line arguments which `cargo test` passes to a `harness = false` binary.
+//!
+//! The supported arguments mirror the ones of the default libtest harness, so
+//! `cargo test --test integration -- some_name --exact --nocapture` behaves the same way
+//! as for a usual test target.
+use std::env;
+use std::process;
+use crate::tags::TagExpr;
+
+const USAGE: &str = "Usage: [OPTIONS] [FILTERS...]
+
+Options:
+        --include-ignored
+                        Run ignored and not ignored tests
+        --ignored       Run only ignored tests
+        --list          List all tests
+        --tags EXPRESSION
+                        Run only tests whose tags match the expression, e.g.
+                        \"smoke and not (db or external)\"
+        --exact         Exactly match filters rather than by substring
+        --skip FILTER   Skip tests whose names contain FILTER (this flag can
+                        be used multiple times)
+        --shuffle       Run tests in random order, also enabled by the
+                        RUST_TEST_SHUFFLE environment variable
+        --shuffle-seed SEED
+                        Run tests in random order, the same seed gives the
+                        same order (or RUST_TEST_SHUFFLE_SEED)
+        --test-threads n_threads
+                        Number of threads used for running tests in parallel
+        --nocapture     Don't capture stdout/stderr of each task, allow
+                        printing directly. Output is not captured by default,
+                        unless TestRunner::with_output_capture is used
+    -q, --quiet         Display one character per test instead of one line.
+                        Alias to --format=terse
+        --format pretty|terse|json
+                        Configure formatting of output
+        --color auto|always|never
+                        Accepted for compatibility, has no effect
+        --show-output, --report-time, --ensure-time, --exclude-should-panic,
+        --force-run-in-process, --logfile PATH, -Z FLAG
+                        Other libtest options, accepted for compatibility
+                        (e.g. with IDE runners), have no effect
+    -h, --help          Display this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunIgnored {
+    /// Run only not ignored tests, the default
+    No,
+    /// Run ignored and not ignored tests, `--include-ignored`
+    Yes,
+    /// Run only ignored tests, `--ignored`
+    Only,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Pretty,
+    Terse,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestArgs {
+    pub filters: Vec<String>,
+    pub exact: bool,
+    pub skip: Vec<String>,
+    /// Tag expression of `--tags`
+    pub tags: Option<String>,
+    pub run_ignored: RunIgnored,
+    pub list: bool,
+    pub shuffle: bool,
+    pub shuffle_seed: Option<u64>,
+    pub test_threads: Option<usize>,
+    pub nocapture: bool,
+    pub format: OutputFormat,
+}
+
+impl Default for TestArgs {
+    fn default() -> Self {
+        TestArgs {
+            filters: Vec::new(),
+            exact: false,
+            skip: Vec::new(),
+            tags: None,
+            run_ignored: RunIgnored::No,
+            list: false,
+            shuffle: false,
+            shuffle_seed: None,
+            test_threads: None,
+            nocapture: false,
+            format: OutputFormat::Pretty,
+        }
+    }
+}
+
+impl TestArgs {
+    /// Parses the arguments of the current process, `RUST_TEST_SHUFFLE` and `RUST_TEST_SHUFFLE_SEED`
+    /// are used unless the corresponding arguments were passed.
+    /// Prints the usage and exits the process if `--help` was passed or the arguments are invalid.
+    pub fn from_env() -> TestArgs {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        let parsed = TestArgs::parse(args).and_then(|mut args| {
+            args.shuffle |= env::var_os("RUST_TEST_SHUFFLE").is_some();
+            if let (None, Ok(seed)) = (args.shuffle_seed, env::var("RUST_TEST_SHUFFLE_SEED")) {
+                args.shuffle_seed = Some(parse_shuffle_seed(&seed)?);
+            }
+            Ok(args)
+        });
+        match parsed {
+            Ok(args) => args,
+            Err(error) => {
+                eprintln!("error: {}\n\n{}", error, USAGE);
+                process::exit(101);
+            }
+        }
+    }
+
+    /// Parses the given arguments, the name of the binary must not be included.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<TestArgs, String> {
+        let mut result = TestArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                result.filters.push(arg);
+                continue;
+            }
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || inline_value.clone()
+                .or_else(|| args.next())
+                .ok_or(format!("Argument to option '{}' missing", flag.trim_start_matches('-')));
+            match flag.as_str() {
+                "--exact" => result.exact = true,
+                "--skip" => result.skip.push(value()?),
+                "--tags" => result.tags = Some(parse_tags(value()?)?),
+                "--ignored" => result.run_ignored = RunIgnored::Only,
+                "--include-ignored" => result.run_ignored = RunIgnored::Yes,
+                "--list" => result.list = true,
+                "--shuffle" => result.shuffle = true,
+                "--shuffle-seed" => result.shuffle_seed = Some(parse_shuffle_seed(&value()?)?),
+                "--nocapture" | "--no-capture" => result.nocapture = true,
+                "-q" | "--quiet" => result.format = OutputFormat::Terse,
+                "--test-threads" => result.test_threads = Some(parse_test_threads(&value()?)?),
+                "--format" => result.format = parse_format(&value()?)?,
+                "--color" | "--logfile" | "-Z" => {
+                    value()?;
+                }
+                // other libtest options, passed e.g. by IDE runners, they have no effect here
+                "--show-output" | "--report-time" | "--ensure-time" | "--exclude-should-panic"
+                | "--force-run-in-process" | "--test" | "--bench" => {}
+                flag if flag.starts_with("-Z") => {}
+                _ => return Err(format!("Unrecognized option: '{}'", flag.trim_start_matches('-'))),
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn parse_test_threads(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("argument for --test-threads must not be 0".to_string()),
+        Ok(threads) => Ok(threads),
+        Err(e) => Err(format!("argument for --test-threads must be a number > 0 (error: {})", e)),
+    }
+}
+
+fn parse_shuffle_seed(value: &str) -> Result<u64, String> {
+    value.parse::<u64>()
+        .map_err(|e| format!("argument for --shuffle-seed must be a number (error: {})", e))
+}
+
+fn parse_tags(value: String) -> Result<String, String> {
+    TagExpr::parse(&value).map(|_| value)
+}
+
+fn parse_format(value: &str) -> Result<OutputFormat, String> {
+    match value {
+        "pretty" => Ok(OutputFormat::Pretty),
+        "terse" => Ok(OutputFormat::Terse),
+        "json" => Ok(OutputFormat::Json),
+        _ => Err(format!("argument for --format must be pretty, terse, or json (was {})", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::{OutputFormat, RunIgnored, TestArgs};
+
+    fn parse(args: &[&str]) -> Result<TestArgs, String> {
+        TestArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_filters_and_flags() {
+        let args = parse(&["some_name", "--exact", "--nocapture", "--skip", "slow", "--skip=db",
+            "--include-ignored", "--test-threads", "4", "--format=json", "--tags", "smoke and not db", "--shuffle-seed", "42"]).unwrap();
+
+        assert_eq!(args.filters, vec!["some_name".to_string()]);
+        assert!(args.exact);
+        assert!(args.nocapture);
+        assert_eq!(args.skip, vec!["slow".to_string(), "db".to_string()]);
+        assert_eq!(args.run_ignored, RunIgnored::Yes);
+        assert_eq!(args.test_threads, Some(4));
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.tags, Some("smoke and not db".to_string()));
+        assert_eq!(args.shuffle_seed, Some(42));
+        assert!(!args.list);
+    }
+
+    #[test]
+    fn parse_defaults() {
+        assert_eq!(parse(&[]).unwrap(), TestArgs::default());
+    }
+
+    #[test]
+    fn parse_ignores_other_libtest_options() {
+        let args = parse(&["-Z", "unstable-options", "--format=json", "--show-output", "-Zunstable-options",
+            "--report-time", "--ensure-time", "--logfile", "tests.log", "--exclude-should-panic",
+            "--force-run-in-process", "some_name"]).unwrap();
+
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.filters, vec!["some_name".to_string()]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--skip"]).is_err());
+        assert!(parse(&["--test-threads", "0"]).is_err());
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["--tags", "smoke and"]).is_err());
+        assert!(parse(&["--shuffle-seed", "-1"]).is_err());
+    }
+}
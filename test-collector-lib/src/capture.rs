@@ -0,0 +1,32 @@
+use std::io::{Read, Write};
+use gag::BufferRedirect;
+use crate::logger::log_error_static_info;
+
+/// Redirects stdout of the process into a buffer while a test is running.
+/// The redirect is process wide, so output of other threads is captured as well.
+pub(crate) struct OutputCapture {
+    redirect: BufferRedirect,
+}
+
+impl OutputCapture {
+    /// Returns None if stdout can not be redirected, e.g. because it is already captured
+    pub(crate) fn start() -> Option<OutputCapture> {
+        let _ = std::io::stdout().flush();
+        match BufferRedirect::stdout() {
+            Ok(redirect) => Some(OutputCapture { redirect }),
+            Err(e) => {
+                log_error_static_info(format_args!("Output of the test will not be captured: {}", e));
+                None
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        let _ = std::io::stdout().flush();
+        let mut output = Vec::new();
+        if let Err(e) = self.redirect.read_to_end(&mut output) {
+            log_error_static_info(format_args!("Failed to read captured output: {}", e));
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
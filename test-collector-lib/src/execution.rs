@@ -0,0 +1,196 @@
+//! Execution of a single test with panic isolation, an optional timeout and interruption.
+//! Details of a panic are recorded per thread, so the failure is built right where the panic was caught.
+use std::any::Any;
+use std::future::Future;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
+use futures::future::{select, Either};
+use futures::FutureExt;
+use futures_timer::Delay;
+use test_collector_utils::{FixtureFn, Fixtures, IntegrationTestMeta, SyncFn};
+use crate::{FailureKind, TestFailure};
+use crate::interrupt::{grace_period_is_over, is_handler_installed};
+use crate::panic_hook::{clear_last_panic, failure_from_panic};
+
+/// How often a running test is checked for the timeout and the interruption
+const DEADLINE_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The test function or closure of a sync test which does not take fixtures
+pub(crate) fn sync_test(test: &IntegrationTestMeta) -> Option<SyncFn> {
+    match test.sync_fn {
+        Some(sync_fn) => Some(Arc::new(sync_fn)),
+        None => test.sync_closure.clone(),
+    }
+}
+
+/// If the timeout is set, the test is executed on a separate thread, otherwise on the current one.
+/// There is no way to stop a thread, so on timeout or interruption the test is left running in the background.
+pub(crate) fn execute_sync(function: SyncFn, timeout: Option<Duration>) -> Result<(), TestFailure> {
+    if timeout.is_none() {
+        return execute_inline(&function);
+    }
+    let started = Instant::now();
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("test-with-timeout".to_string())
+        .spawn(move || {
+            let _ = sender.send(execute_inline(&function));
+        })
+        .expect("Failed to spawn thread for the test");
+    loop {
+        match receiver.recv_timeout(DEADLINE_CHECK_INTERVAL) {
+            Ok(result) => return result,
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(failure) = deadline_failure(started, timeout) {
+                    return Err(failure);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(TestFailure {
+                kind: FailureKind::Panic,
+                message: "Test thread stopped without result".to_string(),
+                location: None,
+                backtrace: None,
+            }),
+        }
+    }
+}
+
+fn execute_inline(function: &SyncFn) -> Result<(), TestFailure> {
+    clear_last_panic();
+    panic::catch_unwind(AssertUnwindSafe(|| function())).map_err(|e| failure_from_panic(e.as_ref()))
+}
+
+/// The fixtures are borrowed from the environment, so the test is executed on the current thread
+/// and can not be abandoned, which is why it can not have a timeout.
+pub(crate) fn execute_with_fixtures(function: FixtureFn, fixtures: &dyn Fixtures) -> Result<(), TestFailure> {
+    clear_last_panic();
+    panic::catch_unwind(AssertUnwindSafe(|| function(fixtures))).map_err(|e| failure_from_panic(e.as_ref()))
+}
+
+/// On timeout or interruption the future of the test is dropped.
+pub(crate) async fn execute_async(test: Pin<Box<dyn Future<Output=()> + '_>>, timeout: Option<Duration>) -> Result<(), TestFailure> {
+    clear_last_panic();
+    let test = AssertUnwindSafe(test)
+        .catch_unwind()
+        .map(|result| result.map_err(|e| failure_from_panic(e.as_ref())));
+    if timeout.is_none() && !is_handler_installed() {
+        return test.await;
+    }
+    let started = Instant::now();
+    let deadline = async move {
+        loop {
+            Delay::new(DEADLINE_CHECK_INTERVAL).await;
+            if let Some(failure) = deadline_failure(started, timeout) {
+                return failure;
+            }
+        }
+    };
+    match select(test, Box::pin(deadline)).await {
+        Either::Left((result, _)) => result,
+        Either::Right((failure, _)) => Err(failure),
+    }
+}
+
+/// Returns the failure if the test has to be abandoned
+fn deadline_failure(started: Instant, timeout: Option<Duration>) -> Option<TestFailure> {
+    if let Some(timeout) = timeout.filter(|timeout| started.elapsed() >= *timeout) {
+        return Some(TestFailure::timed_out(timeout));
+    }
+    if grace_period_is_over() {
+        return Some(TestFailure {
+            kind: FailureKind::Interrupted,
+            message: "Test was still running when the grace period after the interruption was over".to_string(),
+            location: None,
+            backtrace: None,
+        });
+    }
+    None
+}
+
+/// Hooks of the environment are executed on the current thread,
+/// a panic fails the current test instead of unwinding out of the runner.
+pub(crate) fn execute_hook<F: FnOnce()>(hook: &str, function: F) -> Result<(), TestFailure> {
+    clear_last_panic();
+    panic::catch_unwind(AssertUnwindSafe(function)).map_err(|e| hook_failure(hook, e.as_ref()))
+}
+
+/// Async hooks are awaited by the caller, so they can be driven by an already running `block_on`
+pub(crate) async fn execute_async_hook<F: Future<Output=()>>(hook: &str, hook_future: F) -> Result<(), TestFailure> {
+    clear_last_panic();
+    AssertUnwindSafe(hook_future)
+        .catch_unwind()
+        .await
+        .map_err(|e| hook_failure(hook, e.as_ref()))
+}
+
+fn hook_failure(hook: &str, payload: &(dyn Any + Send)) -> TestFailure {
+    let failure = failure_from_panic(payload);
+    TestFailure {
+        kind: FailureKind::Hook,
+        message: format!("{} panicked: {}", hook, failure.message),
+        ..failure
+    }
+}
+
+/// Expected outcome of a test marked with `#[collect_test(should_panic)]`
+pub(crate) struct ShouldPanic {
+    expected: Option<String>,
+}
+
+impl ShouldPanic {
+    pub(crate) fn of(test: &IntegrationTestMeta) -> Option<ShouldPanic> {
+        if test.should_panic {
+            Some(ShouldPanic { expected: test.expected_panic.clone() })
+        } else {
+            None
+        }
+    }
+
+    /// Inverts the outcome like libtest does, the test passes if it panicked
+    /// with a message containing the expected substring. Timeouts stay failures.
+    pub(crate) fn check(&self, result: Result<(), TestFailure>) -> Result<(), TestFailure> {
+        let failure = match result {
+            Ok(_) => return Err(TestFailure {
+                kind: FailureKind::ShouldPanic,
+                message: "test did not panic as expected".to_string(),
+                location: None,
+                backtrace: None,
+            }),
+            Err(failure) if failure.kind != FailureKind::Panic => return Err(failure),
+            Err(failure) => failure,
+        };
+        match &self.expected {
+            Some(expected) if !failure.message.contains(expected.as_str()) => Err(TestFailure {
+                kind: FailureKind::ShouldPanic,
+                message: format!("panic did not contain expected string\n      panic message: {:?}\n expected substring: {:?}",
+                                 failure.message, expected),
+                ..failure
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Applies `should_panic` of the test, if it has one
+pub(crate) fn expect_outcome(should_panic: Option<&ShouldPanic>, result: Result<(), TestFailure>) -> Result<(), TestFailure> {
+    match should_panic {
+        Some(should_panic) => should_panic.check(result),
+        None => result,
+    }
+}
+
+impl TestFailure {
+    fn timed_out(timeout: Duration) -> TestFailure {
+        TestFailure {
+            kind: FailureKind::Timeout,
+            message: format!("Test exceeded timeout of {:?}", timeout),
+            location: None,
+            backtrace: None,
+        }
+    }
+}
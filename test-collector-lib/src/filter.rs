@@ -0,0 +1,99 @@
+use regex::Regex;
+use test_collector_utils::IntegrationTestMeta;
+use crate::args::{RunIgnored, TestArgs};
+use crate::tags::TagExpr;
+
+pub(crate) enum NamePattern {
+    Substring(String),
+    Exact(String),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub(crate) fn regex(pattern: &str) -> NamePattern {
+        let regex = Regex::new(pattern)
+            .unwrap_or_else(|e| panic!("Invalid test name regex [{}]: {}", pattern, e));
+        NamePattern::Regex(regex)
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Substring(substring) => name.contains(substring.as_str()),
+            NamePattern::Exact(exact) => name == exact,
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Decides which of the collected tests should be executed.
+/// A test is selected if it matches any of the included patterns (or there are none)
+/// and does not match any of the skipped ones. Tags of the test must match all the tag expressions.
+pub(crate) struct TestFilter {
+    include: Vec<NamePattern>,
+    skip: Vec<NamePattern>,
+    tags: Vec<TagExpr>,
+    run_ignored: RunIgnored,
+}
+
+impl TestFilter {
+    pub(crate) fn new() -> TestFilter {
+        TestFilter {
+            include: Vec::new(),
+            skip: Vec::new(),
+            tags: Vec::new(),
+            run_ignored: RunIgnored::No,
+        }
+    }
+
+    pub(crate) fn include(&mut self, pattern: NamePattern) {
+        self.include.push(pattern);
+    }
+
+    pub(crate) fn skip(&mut self, pattern: NamePattern) {
+        self.skip.push(pattern);
+    }
+
+    /// Panics if the expression is invalid
+    pub(crate) fn tags(&mut self, expression: &str) {
+        let expr = TagExpr::parse(expression)
+            .unwrap_or_else(|e| panic!("Invalid tag expression [{}]: {}", expression, e));
+        self.tags.push(expr);
+    }
+
+    pub(crate) fn set_run_ignored(&mut self, run_ignored: RunIgnored) {
+        self.run_ignored = run_ignored;
+    }
+
+    pub(crate) fn run_ignored(&self) -> RunIgnored {
+        self.run_ignored
+    }
+
+    pub(crate) fn apply_args(&mut self, args: &TestArgs) {
+        let to_pattern = |value: &String| if args.exact {
+            NamePattern::Exact(value.clone())
+        } else {
+            NamePattern::Substring(value.clone())
+        };
+        self.include.extend(args.filters.iter().map(to_pattern));
+        self.skip.extend(args.skip.iter().map(to_pattern));
+        if let Some(tags) = &args.tags {
+            self.tags(tags);
+        }
+        if args.run_ignored != RunIgnored::No {
+            self.run_ignored = args.run_ignored;
+        }
+    }
+
+    pub(crate) fn matches(&self, test: &IntegrationTestMeta) -> bool {
+        if self.run_ignored == RunIgnored::Only && !test.ignored {
+            return false;
+        }
+        if !self.tags.iter().all(|expr| expr.matches(&test.tags)) {
+            return false;
+        }
+        let name = test.full_name();
+        let included = self.include.is_empty()
+            || self.include.iter().any(|pattern| pattern.matches(&name));
+        included && !self.skip.iter().any(|pattern| pattern.matches(&name))
+    }
+}
@@ -0,0 +1,35 @@
+//! Fixtures of the tests which take references, e.g. `fn user_is_stored(env: &MyEnv, pool: &PgPool)`.
+use std::any::{Any, TypeId};
+use std::future::Future;
+use std::pin::Pin;
+use test_collector_utils::{Fixtures, IntegrationTestMeta};
+use crate::TestEnvironment;
+
+/// Provides the environment itself and the fixtures returned by `TestEnvironment::fixture`
+pub(crate) struct EnvironmentFixtures<'a, T: TestEnvironment> {
+    environment: &'a T,
+}
+
+impl<'a, T: TestEnvironment> EnvironmentFixtures<'a, T> {
+    pub(crate) fn new(environment: &'a T) -> EnvironmentFixtures<'a, T> {
+        EnvironmentFixtures { environment }
+    }
+}
+
+impl<T: TestEnvironment + 'static> Fixtures for EnvironmentFixtures<'_, T> {
+    fn fixture(&self, fixture: TypeId) -> Option<&dyn Any> {
+        if fixture == TypeId::of::<T>() {
+            Some(self.environment)
+        } else {
+            self.environment.fixture(fixture)
+        }
+    }
+}
+
+/// Creates the future of an async test, with or without fixtures
+pub(crate) fn async_test<'a>(test: &'a IntegrationTestMeta, fixtures: &'a dyn Fixtures) -> Pin<Box<dyn Future<Output=()> + 'a>> {
+    match test.async_fixture_fn {
+        Some(async_fixture_fn) => async_fixture_fn(fixtures),
+        None => (test.async_fn.as_ref().expect("Test must be async"))(),
+    }
+}
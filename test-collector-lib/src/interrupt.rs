@@ -0,0 +1,60 @@
+//! Handling of SIGINT (Ctrl-C) and SIGTERM, so the environment is still stopped when the run is interrupted.
+//! The first signal stops the run, running tests get a grace period to finish,
+//! the second signal exits the process right away.
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, Once};
+use std::time::{Duration, Instant};
+use crate::logger::log_error_static_info;
+
+static INSTALL_HANDLER: Once = Once::new();
+static HANDLER_INSTALLED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+static GRACE_PERIOD_MILLIS: AtomicU64 = AtomicU64::new(0);
+
+/// Installs the signal handler once per process. Fails if the application has its own handler already.
+pub(crate) fn install_interrupt_handler(grace_period: Duration) {
+    GRACE_PERIOD_MILLIS.store(grace_period.as_millis() as u64, Ordering::SeqCst);
+    INSTALL_HANDLER.call_once(|| {
+        match ctrlc::set_handler(on_signal) {
+            Ok(_) => HANDLER_INSTALLED.store(true, Ordering::SeqCst),
+            Err(e) => log_error_static_info(format_args!("Failed to install SIGINT/SIGTERM handler: {}", e)),
+        }
+    });
+}
+
+/// Whether running async tests must be interruptible
+pub(crate) fn is_handler_installed() -> bool {
+    HANDLER_INSTALLED.load(Ordering::SeqCst)
+}
+
+/// Forgets the interruption of a previous run in the same process
+pub(crate) fn reset_interruption() {
+    *interrupted_at() = None;
+}
+
+pub(crate) fn is_interrupted() -> bool {
+    interrupted_at().is_some()
+}
+
+/// Running tests are abandoned once the grace period after the interruption is over
+pub(crate) fn grace_period_is_over() -> bool {
+    match *interrupted_at() {
+        Some(interrupted_at) => interrupted_at.elapsed() >= Duration::from_millis(GRACE_PERIOD_MILLIS.load(Ordering::SeqCst)),
+        None => false,
+    }
+}
+
+fn interrupted_at() -> MutexGuard<'static, Option<Instant>> {
+    INTERRUPTED_AT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn on_signal() {
+    let mut interrupted_at = interrupted_at();
+    if interrupted_at.is_some() {
+        log_error_static_info(format_args!("Interrupted again, exiting without stopping test environment"));
+        process::exit(130);
+    }
+    *interrupted_at = Some(Instant::now());
+    log_error_static_info(format_args!("Interrupted, no more tests are started and test environment is going to be stopped"));
+}
@@ -0,0 +1,275 @@
+//! JUnit XML serialization of the `TestResults`, the format most of the CI systems can ingest.
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use crate::{FailureKind, TestFailure, TestResult, TestResults};
+
+impl TestResults {
+    /// Serializes the results as JUnit XML with a single test suite with the given name.
+    /// Start up and stop durations of the environment are stored as suite properties.
+    pub fn to_junit_xml(&self, suite_name: &str) -> String {
+        let skipped = self.ignored_tests.len() + self.not_run_tests.len();
+        let tests = self.success_tests.len() + self.flaky_tests.len() + self.failed_tests.len() + skipped;
+        let errors = self.failed_tests.iter()
+            .filter(|test| test.failure.as_ref().map(|failure| failure.kind).is_some_and(is_error))
+            .count();
+        let failures = self.failed_tests.len() - errors;
+        let time = seconds(self.tests_duration);
+
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(xml, r#"<testsuites name="{}" tests="{}" failures="{}" errors="{}" time="{}">"#,
+                         escape(suite_name), tests, failures, errors, time);
+        let _ = writeln!(xml, r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" time="{}">"#,
+                         escape(suite_name), tests, failures, errors, skipped, time);
+        xml.push_str("    <properties>\n");
+        write_property(&mut xml, "start_up_duration", &seconds(self.start_up_duration));
+        write_property(&mut xml, "stop_duration", &seconds(self.stop_duration));
+        if let Some(error) = &self.start_error {
+            write_property(&mut xml, "start_error", error);
+        }
+        if let Some(error) = &self.stop_error {
+            write_property(&mut xml, "stop_error", error);
+        }
+        if self.interrupted {
+            write_property(&mut xml, "interrupted", "true");
+        }
+        if let Some(seed) = self.shuffle_seed {
+            write_property(&mut xml, "shuffle_seed", &seed.to_string());
+        }
+        for suite in &self.suites {
+            let prefix = format!("suite.{}", suite.name);
+            write_property(&mut xml, &format!("{}.start_up_duration", prefix), &seconds(suite.start_up_duration));
+            write_property(&mut xml, &format!("{}.tests_duration", prefix), &seconds(suite.tests_duration));
+            write_property(&mut xml, &format!("{}.stop_duration", prefix), &seconds(suite.stop_duration));
+            if let Some(error) = &suite.start_error {
+                write_property(&mut xml, &format!("{}.start_error", prefix), error);
+            }
+            if let Some(error) = &suite.stop_error {
+                write_property(&mut xml, &format!("{}.stop_error", prefix), error);
+            }
+        }
+        xml.push_str("    </properties>\n");
+        for test in &self.success_tests {
+            write_test_case(&mut xml, test);
+        }
+        for test in &self.flaky_tests {
+            write_test_case(&mut xml, test);
+        }
+        for test in &self.failed_tests {
+            write_test_case(&mut xml, test);
+        }
+        for test in self.ignored_tests.iter().chain(&self.not_run_tests) {
+            write_skipped_test_case(&mut xml, test);
+        }
+        xml.push_str("  </testsuite>\n");
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    /// Writes JUnit XML report to the given path, parent directories are created if needed.
+    pub fn write_junit_xml<P: AsRef<Path>>(&self, suite_name: &str, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_junit_xml(suite_name))
+    }
+}
+
+fn write_property(xml: &mut String, name: &str, value: &str) {
+    let _ = writeln!(xml, r#"      <property name="{}" value="{}"/>"#, escape(name), escape(value));
+}
+
+fn write_test_case_start(xml: &mut String, test: &TestResult) {
+    let (class_name, name) = match test.name.rsplit_once("::") {
+        Some((module_path, name)) => (module_path, name),
+        None => ("", test.name.as_str()),
+    };
+    let _ = write!(xml, r#"    <testcase name="{}" classname="{}" time="{}""#,
+                   escape(name), escape(class_name), seconds(test.duration));
+}
+
+fn write_skipped_test_case(xml: &mut String, test: &TestResult) {
+    write_test_case_start(xml, test);
+    xml.push_str(">\n");
+    match &test.skip_reason {
+        Some(reason) => {
+            let _ = writeln!(xml, r#"      <skipped message="{}"/>"#, escape(reason));
+        }
+        None => xml.push_str("      <skipped/>\n"),
+    }
+    xml.push_str("    </testcase>\n");
+}
+
+fn write_test_case(xml: &mut String, test: &TestResult) {
+    write_test_case_start(xml, test);
+    if test.success && test.output.is_none() && test.attempts <= 1 {
+        xml.push_str("/>\n");
+        return;
+    }
+    xml.push_str(">\n");
+    if test.attempts > 1 {
+        xml.push_str("      <properties>\n");
+        let _ = writeln!(xml, r#"        <property name="attempts" value="{}"/>"#, test.attempts);
+        xml.push_str("      </properties>\n");
+    }
+    if !test.success {
+        write_failure(xml, test.failure.as_ref());
+    }
+    if let Some(output) = &test.output {
+        let _ = writeln!(xml, "      <system-out>{}</system-out>", escape(output));
+    }
+    xml.push_str("    </testcase>\n");
+}
+
+fn write_failure(xml: &mut String, failure: Option<&TestFailure>) {
+    let failure = match failure {
+        Some(failure) => failure,
+        None => {
+            xml.push_str("      <failure message=\"test failed\" type=\"panic\"/>\n");
+            return;
+        }
+    };
+    let mut details = String::new();
+    if let Some(location) = &failure.location {
+        let _ = writeln!(details, "panicked at {}", location);
+    }
+    details.push_str(&failure.message);
+    if let Some(backtrace) = &failure.backtrace {
+        let _ = write!(details, "\n{}", backtrace);
+    }
+    let (element, failure_type) = match failure.kind {
+        FailureKind::Panic => ("failure", "panic"),
+        FailureKind::Timeout => ("failure", "timeout"),
+        FailureKind::ShouldPanic => ("failure", "should_panic"),
+        FailureKind::Setup => ("error", "setup"),
+        FailureKind::Hook => ("error", "hook"),
+        FailureKind::Interrupted => ("error", "interrupted"),
+    };
+    let _ = writeln!(xml, r#"      <{} message="{}" type="{}">{}</{}>"#,
+                     element, escape(&failure.message), failure_type, escape(&details), element);
+}
+
+/// Errors are problems of the environment rather than of the test itself
+fn is_error(kind: FailureKind) -> bool {
+    matches!(kind, FailureKind::Setup | FailureKind::Hook | FailureKind::Interrupted)
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            // not allowed in XML 1.0 even when escaped
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::{FailureKind, SuiteResults, TestFailure, TestResult, TestResults};
+
+    #[test]
+    fn serialize_results() {
+        let results = TestResults {
+            success_tests: vec![TestResult {
+                name: "my_crate::users::create_user".to_string(),
+                success: true,
+                duration: Duration::from_millis(1500),
+                output: None,
+                failure: None,
+                skip_reason: None,
+                attempts: 1,
+            }],
+            failed_tests: vec![TestResult {
+                name: "delete_user".to_string(),
+                success: false,
+                duration: Duration::from_millis(20),
+                output: Some("<user> & \"friends\"\n".to_string()),
+                failure: Some(TestFailure {
+                    kind: FailureKind::Panic,
+                    message: "user was not deleted".to_string(),
+                    location: Some("tests/users.rs:10:5".to_string()),
+                    backtrace: None,
+                }),
+                skip_reason: None,
+                attempts: 1,
+            }],
+            ignored_tests: vec![TestResult {
+                name: "my_crate::payments::refund".to_string(),
+                success: false,
+                duration: Duration::ZERO,
+                output: None,
+                failure: None,
+                skip_reason: Some("sandbox is down".to_string()),
+                attempts: 0,
+            }],
+            not_run_tests: Vec::new(),
+            flaky_tests: vec![TestResult {
+                name: "my_crate::payments::charge".to_string(),
+                success: true,
+                duration: Duration::from_millis(100),
+                output: None,
+                failure: None,
+                skip_reason: None,
+                attempts: 2,
+            }],
+            filtered_out: 0,
+            start_error: None,
+            stop_error: None,
+            interrupted: false,
+            suites: vec![SuiteResults {
+                name: "billing".to_string(),
+                passed: 1,
+                failed: 0,
+                flaky: 1,
+                not_run: 0,
+                start_error: None,
+                stop_error: Some("connection reset".to_string()),
+                start_up_duration: Duration::from_millis(500),
+                tests_duration: Duration::from_secs(1),
+                stop_duration: Duration::ZERO,
+            }],
+            shuffle_seed: Some(42),
+            start_up_duration: Duration::from_secs(2),
+            tests_duration: Duration::from_secs(3),
+            stop_duration: Duration::from_millis(250),
+        };
+
+        let xml = results.to_junit_xml("integration");
+
+        assert!(xml.contains(r#"<testsuite name="integration" tests="4" failures="1" errors="0" skipped="1" time="3.000">"#));
+        assert!(xml.contains(r#"<property name="start_up_duration" value="2.000"/>"#));
+        assert!(xml.contains(r#"<property name="stop_duration" value="0.250"/>"#));
+        assert!(xml.contains(r#"<property name="shuffle_seed" value="42"/>"#));
+        assert!(xml.contains(r#"<property name="suite.billing.start_up_duration" value="0.500"/>"#));
+        assert!(xml.contains(r#"<property name="suite.billing.stop_error" value="connection reset"/>"#));
+        assert!(xml.contains(r#"<testcase name="create_user" classname="my_crate::users" time="1.500"/>"#));
+        assert!(xml.contains(r#"<testcase name="delete_user" classname="" time="0.020">"#));
+        assert!(xml.contains(r#"<failure message="user was not deleted" type="panic">panicked at tests/users.rs:10:5"#));
+        assert!(xml.contains(r#"<testcase name="refund" classname="my_crate::payments" time="0.000">
+      <skipped message="sandbox is down"/>"#));
+        assert!(xml.contains(r#"<testcase name="charge" classname="my_crate::payments" time="0.100">
+      <properties>
+        <property name="attempts" value="2"/>
+      </properties>
+    </testcase>"#));
+        assert!(xml.contains("<system-out>&lt;user&gt; &amp; &quot;friends&quot;\n</system-out>"));
+    }
+}
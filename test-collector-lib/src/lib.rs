@@ -1,6 +1,8 @@
 //! ## Usage
 //! Do not forget to modify Cargo.toml with.
 //! If you want you can override before_each_test and after_each_test from the TestEnvironment trait. By default these methods are empty.
+//! The runner applies the usual libtest arguments of the process, e.g. `cargo test --test integration -- some_name --exact`,
+//! use `TestRunner::with_env_args(false)` to ignore them.
 //! ```toml
 //! [[test]]
 //! name = "integration"
@@ -8,7 +10,7 @@
 //! harness = false
 //! ```
 //!
-//! ``` rust
+//! ``` rust,no_run
 //!    use std::future::Future;
 //!    use std::thread;
 //!    use actix_web::{App, HttpResponse, HttpServer, Responder};
@@ -53,9 +55,7 @@
 //!    }
 //!
 //!
-//!    #[test]
-//!    #[should_panic(expected = "Some tests are Failing")]
-//!    fn possible_main() {
+//!    fn main() {
 //!     let system = actix_web::rt::System::new();
 //!     let test_runner = TestRunner::new(MockTestEnv{system});
 //!     test_runner.run();
@@ -78,34 +78,157 @@
 //! ```
 
 pub mod test_runner;
+pub mod args;
+mod capture;
+mod execution;
+mod filter;
+mod fixtures;
+mod interrupt;
+mod junit;
+mod panic_hook;
+mod report;
+mod shuffle;
+mod tags;
+mod worker_pool;
 mod logger;
 
 extern crate core;
 
+use std::any::{Any, TypeId};
+use std::error::Error;
 use std::fmt::Arguments;
 use std::future::Future;
 use std::time::{Duration};
 use crate::logger::log_static_info;
 
+pub use test_collector_utils::IntegrationTestMeta;
+
 pub trait TestEnvironment {
     fn start(self) -> Self;
 
-    fn before_each_test(&self) {
+    /// Invoked before every test (and every retry of it), e.g. to create a database schema named after the test
+    fn before_each_test(&self, _test: &IntegrationTestMeta) {
         // do nothing by default
     }
 
     fn block_on<F: Future>(&self, fut: F) -> F::Output;
 
-    fn after_each_test(&self) {
+    /// Invoked after every test with its result, e.g. to dump logs of the failed tests.
+    /// The result does not include the failure of the hooks themselves.
+    fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
         // do nothing by default
     }
 
     fn stop(self) -> Self;
+
+    /// Typed fixtures which tests can take by reference besides the environment itself,
+    /// e.g. `fn user_is_stored(pool: &PgPool)` gets `Some(&self.pool)` returned for `TypeId::of::<PgPool>()`.
+    fn fixture(&self, _fixture: TypeId) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Fallible variant of `start` which is invoked by the runner, calls `start` by default.
+    /// If it fails, all tests are reported as failed with `FailureKind::Setup`
+    /// and `try_stop` is still invoked on the returned environment to clean up.
+    fn try_start(self) -> Result<Self, EnvironmentError<Self>> where Self: Sized {
+        Ok(self.start())
+    }
+
+    /// Fallible variant of `stop` which is invoked by the runner, calls `stop` by default.
+    /// The error is recorded in `TestResults::stop_error`.
+    fn try_stop(self) -> Result<Self, EnvironmentError<Self>> where Self: Sized {
+        Ok(self.stop())
+    }
+
+    /// Async part of the start up, e.g. running migrations, driven by `block_on` right after `try_start`.
+    /// An error or a panic is handled the same way as an error of `try_start`.
+    fn start_async(&self) -> impl Future<Output=Result<(), Box<dyn Error + Send + Sync>>> {
+        async { Ok(()) }
+    }
+
+    /// Async counterpart of `before_each_test`, awaited right after it
+    fn before_each_test_async(&self, _test: &IntegrationTestMeta) -> impl Future<Output=()> {
+        async {}
+    }
+
+    /// Async counterpart of `after_each_test`, awaited right before it
+    fn after_each_test_async(&self, _test: &IntegrationTestMeta, _result: &TestResult) -> impl Future<Output=()> {
+        async {}
+    }
+
+    /// Async part of the teardown, driven by `block_on` right before `try_stop`.
+    /// `try_stop` is invoked even if it fails or panics, the error is recorded in `TestResults::stop_error`.
+    fn stop_async(&self) -> impl Future<Output=Result<(), Box<dyn Error + Send + Sync>>> {
+        async { Ok(()) }
+    }
+}
+
+/// Set up and tear down of the tests marked with `#[collect_test(suite = "billing")]`,
+/// registered with `TestRunner::with_suite`. Invoked on the thread which owns the environment.
+pub trait TestSuite<T: TestEnvironment> {
+    /// Invoked before the first test of the suite, a panic fails all tests of the suite with `FailureKind::Setup`
+    fn start(&mut self, _environment: &T) {
+        // do nothing by default
+    }
+
+    /// Invoked after the last test of the suite, even if `start` panicked
+    fn stop(&mut self, _environment: &T) {
+        // do nothing by default
+    }
+}
+
+/// Error of `TestEnvironment::try_start` or `try_stop`,
+/// holds the environment, so the runner can still stop it.
+pub struct EnvironmentError<T> {
+    pub environment: T,
+    pub error: Box<dyn Error + Send + Sync>,
+}
+
+impl<T> EnvironmentError<T> {
+    pub fn new<E: Into<Box<dyn Error + Send + Sync>>>(environment: T, error: E) -> EnvironmentError<T> {
+        EnvironmentError {
+            environment,
+            error: error.into(),
+        }
+    }
 }
 
 pub struct TestResults {
     pub success_tests: Vec<TestResult>,
     pub failed_tests: Vec<TestResult>,
+    /// Tests marked with `#[collect_test(ignore)]`, they are not executed unless `--ignored` or `--include-ignored` is passed
+    pub ignored_tests: Vec<TestResult>,
+    /// Tests which passed only after a retry, they do not fail the run
+    pub flaky_tests: Vec<TestResult>,
+    /// Tests which were not started because the run was stopped, e.g. by fail-fast
+    pub not_run_tests: Vec<TestResult>,
+    pub filtered_out: usize,
+    /// Error of `TestEnvironment::try_start`, no test was executed if it is set
+    pub start_error: Option<String>,
+    /// Error of `TestEnvironment::try_stop`
+    pub stop_error: Option<String>,
+    /// The run was stopped by SIGINT or SIGTERM, the remaining tests are in `not_run_tests`
+    pub interrupted: bool,
+    /// Results of the suites in the order they were executed, the tests are in the lists above as well
+    pub suites: Vec<SuiteResults>,
+    /// Seed of the random order of the tests, pass it to `--shuffle-seed` to get the same order again
+    pub shuffle_seed: Option<u64>,
+    pub start_up_duration: Duration,
+    pub tests_duration: Duration,
+    pub stop_duration: Duration,
+}
+
+/// Counts and durations of the tests of one suite
+pub struct SuiteResults {
+    pub name: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub flaky: usize,
+    pub not_run: usize,
+    /// Panic message of `TestSuite::start`, all tests of the suite failed if it is set
+    pub start_error: Option<String>,
+    /// Panic message of `TestSuite::stop`
+    pub stop_error: Option<String>,
     pub start_up_duration: Duration,
     pub tests_duration: Duration,
     pub stop_duration: Duration,
@@ -115,6 +238,40 @@ pub struct TestResult {
     pub name: String,
     pub success: bool,
     pub duration: Duration,
+    /// Captured stdout of the test, None if output capture is disabled
+    pub output: Option<String>,
+    /// Details of the panic, None for successful tests
+    pub failure: Option<TestFailure>,
+    /// Why the test was not executed, e.g. the reason of `#[collect_test(ignore = "reason")]`
+    pub skip_reason: Option<String>,
+    /// Number of times the test was executed, greater than 1 if it was retried
+    pub attempts: u32,
+}
+
+pub struct TestFailure {
+    pub kind: FailureKind,
+    /// Panic message, e.g. the formatted message of a failed `assert_eq!`
+    pub message: String,
+    /// `file:line:column` where the test panicked
+    pub location: Option<String>,
+    /// Captured if `RUST_BACKTRACE` is set or backtraces are enabled on the runner
+    pub backtrace: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The test panicked, e.g. an assertion failed
+    Panic,
+    /// The test did not finish within its timeout
+    Timeout,
+    /// The test marked with `should_panic` did not panic or panicked with an unexpected message
+    ShouldPanic,
+    /// The test was not executed because the environment failed to start
+    Setup,
+    /// `before_each_test` or `after_each_test` panicked
+    Hook,
+    /// The test did not finish within the grace period after the run was interrupted
+    Interrupted,
 }
 
 pub fn log_env_info(message: Arguments) {
@@ -122,6 +279,7 @@ pub fn log_env_info(message: Arguments) {
 }
 
 #[cfg(test)]
+#[allow(clippy::eq_op)]
 mod tests {
     use std::future::Future;
     use std::sync::atomic::{AtomicU32, Ordering};
@@ -132,8 +290,12 @@ mod tests {
     use test_collector_derive::collect_test;
     use Ordering::SeqCst;
     use std::rc::Rc;
+    use std::net::TcpStream;
+    use std::time::Duration;
     use crate::test_runner::TestRunner;
-    use crate::{log_env_info, TestEnvironment};
+    use crate::args::TestArgs;
+    use test_collector_utils::IntegrationTestMeta;
+    use crate::{log_env_info, TestEnvironment, TestResult};
 
     struct MockTestEnv {
         system: SystemRunner,
@@ -144,21 +306,12 @@ mod tests {
     impl TestEnvironment for MockTestEnv {
         fn start(self) -> Self {
             log_env_info(format_args!("Starting environment"));
+            start_server();
             log_env_info(format_args!("Setup of environment Finished"));
-            thread::spawn(move || {
-                actix_web::rt::System::new().block_on(async move {
-                    HttpServer::new(move || App::new()
-                        .service(hello)
-                    )
-                        .bind("127.0.0.1:9090")?
-                        .run()
-                        .await
-                })
-            });
-            return self;
+            self
         }
 
-        fn before_each_test(&self) {
+        fn before_each_test(&self, _test: &IntegrationTestMeta) {
             self.before_each_call.fetch_add(1, SeqCst);
         }
 
@@ -166,7 +319,7 @@ mod tests {
             self.system.block_on(fut)
         }
 
-        fn after_each_test(&self) {
+        fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
             self.after_each_call.fetch_add(1, SeqCst);
         }
 
@@ -174,10 +327,31 @@ mod tests {
             log_env_info(format_args!("Teardown started"));
             log_env_info(format_args!("Here You can stop APP, db or any other services"));
             log_env_info(format_args!("Teardown finished"));
-            return self;
+            self
         }
     }
 
+    static SERVER: OnceCell<()> = OnceCell::new();
+
+    /// Starts the server once for all tests and waits until it accepts connections
+    fn start_server() {
+        SERVER.get_or_init(|| {
+            thread::spawn(move || {
+                actix_web::rt::System::new().block_on(async move {
+                    HttpServer::new(move || App::new()
+                        .service(hello)
+                    )
+                        .bind("127.0.0.1:9090")?
+                        .run()
+                        .await
+                })
+            });
+            while TcpStream::connect("127.0.0.1:9090").is_err() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+    }
+
     #[actix_web::get("/")]
     async fn hello() -> impl Responder {
         HttpResponse::Ok().body("Hello, world!")
@@ -194,7 +368,7 @@ mod tests {
                 before_each_call: Rc::new(AtomicU32::new(0)),
                 after_each_call: Rc::new(AtomicU32::new(0)),
             }
-        );
+        ).with_env_args(false);
 
         test_runner.run();
     }
@@ -210,13 +384,132 @@ mod tests {
                 before_each_call: before_each_call.clone(),
                 after_each_call: after_each_call.clone(),
             }
-        );
+        ).with_env_args(false);
 
         test_runner.run_safe();
         assert_eq!(before_each_call.fetch_or(0, SeqCst), 4);
         assert_eq!(after_each_call.fetch_or(0, SeqCst), 4);
     }
 
+    #[test]
+    fn run_only_tests_matching_args() {
+        let system = actix_web::rt::System::new();
+        let args = TestArgs::parse(["success", "--skip", "async", "--nocapture"].map(String::from))
+            .unwrap();
+        let test_runner = TestRunner::new(
+            MockTestEnv {
+                system,
+                before_each_call: Rc::new(AtomicU32::new(0)),
+                after_each_call: Rc::new(AtomicU32::new(0)),
+            }
+        ).with_args(args);
+
+        let result = test_runner.run_safe();
+        assert_eq!(result.success_tests.len(), 1);
+        assert_eq!(result.success_tests[0].name, "test_collector::tests::sync_test_success");
+        assert!(result.failed_tests.is_empty());
+        assert_eq!(result.filtered_out, 3);
+    }
+
+    #[test]
+    fn run_only_filtered_tests() {
+        let system = actix_web::rt::System::new();
+        let test_runner = TestRunner::new(
+            MockTestEnv {
+                system,
+                before_each_call: Rc::new(AtomicU32::new(0)),
+                after_each_call: Rc::new(AtomicU32::new(0)),
+            }
+        )
+            .with_env_args(false)
+            .filter_regex("::async_[a-z_]*$")
+            .filter_exact("test_collector::tests::sync_test_success")
+            .skip("failing");
+
+        let result = test_runner.run_safe();
+        let mut names: Vec<String> = result.success_tests.into_iter().map(|test| test.name).collect();
+        names.sort();
+        assert_eq!(names, vec![
+            "test_collector::tests::async_test_success".to_string(),
+            "test_collector::tests::sync_test_success".to_string(),
+        ]);
+        assert!(result.failed_tests.is_empty());
+        assert_eq!(result.filtered_out, 2);
+    }
+
+    struct NotStartableEnv;
+
+    impl TestEnvironment for NotStartableEnv {
+        fn start(self) -> Self {
+            panic!("List mode must not start the environment");
+        }
+
+        fn block_on<F: Future>(&self, _fut: F) -> F::Output {
+            panic!("List mode must not run tests");
+        }
+
+        fn stop(self) -> Self {
+            panic!("List mode must not stop the environment");
+        }
+    }
+
+    #[test]
+    fn list_tests_without_starting_environment() {
+        let result = TestRunner::new(NotStartableEnv)
+            .with_env_args(false)
+            .with_list()
+            .skip("failing")
+            .run_safe();
+
+        assert!(result.success_tests.is_empty());
+        assert!(result.failed_tests.is_empty());
+        assert_eq!(result.filtered_out, 2);
+        TestRunner::new(NotStartableEnv).with_args(TestArgs::parse(["--list".to_string()]).unwrap()).run();
+    }
+
+    #[test]
+    fn write_junit_report() {
+        let system = actix_web::rt::System::new();
+        let report_path = std::env::temp_dir()
+            .join(format!("test-collector-{}", std::process::id()))
+            .join("junit.xml");
+        let test_runner = TestRunner::new(
+            MockTestEnv {
+                system,
+                before_each_call: Rc::new(AtomicU32::new(0)),
+                after_each_call: Rc::new(AtomicU32::new(0)),
+            }
+        )
+            .with_env_args(false)
+            .filter_exact("test_collector::tests::sync_test_failing")
+            .with_output_capture(true)
+            .with_junit_report(&report_path);
+
+        let result = test_runner.run_safe();
+        assert_eq!(result.failed_tests.len(), 1);
+        assert!(result.failed_tests[0].output.is_some());
+        let failure = result.failed_tests[0].failure.as_ref().unwrap();
+        assert!(failure.message.contains("assertion `left == right` failed"));
+        assert!(failure.location.as_ref().unwrap().starts_with(file!()));
+        let report = std::fs::read_to_string(&report_path).unwrap();
+        assert!(report.contains(r#"<testcase name="sync_test_failing" classname="test_collector::tests""#));
+        assert!(report.contains(r#"<failure message="assertion `left == right` failed"#));
+        let _ = std::fs::remove_dir_all(report_path.parent().unwrap());
+    }
+
+    #[test]
+    fn collected_test_knows_its_location() {
+        let test = inventory::iter::<IntegrationTestMeta>.into_iter()
+            .find(|test| test.name == "sync_test_failing")
+            .unwrap();
+        let location = test.location.as_ref().unwrap();
+
+        assert_eq!(test.full_name(), "test_collector::tests::sync_test_failing");
+        assert_eq!(location.file, file!());
+        assert_eq!(location.line, line!() + 5);
+        assert_eq!(location.column, 12);
+    }
+
     #[collect_test]
     pub fn sync_test_failing() {
         println!("Executed sync!");
@@ -242,6 +535,7 @@ mod tests {
     #[collect_test(async)]
     #[actix_web::test]
     pub async fn async_test_success() {
+        start_server();
         let client = reqwest::Client::builder()
             .build()
             .expect("error during client build");
@@ -7,8 +7,8 @@ use once_cell::sync::OnceCell;
 use std::io::Write;
 use log::Level::Error;
 
-const LOGGER_TESTING: OnceCell<Logger> = OnceCell::new();
-const LOGGER_STATIC_INFO: OnceCell<Logger> = OnceCell::new();
+static LOGGER_TESTING: OnceCell<Logger> = OnceCell::new();
+static LOGGER_STATIC_INFO: OnceCell<Logger> = OnceCell::new();
 
 
 fn create_testing_logger() -> Logger {
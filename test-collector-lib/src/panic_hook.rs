@@ -0,0 +1,81 @@
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::panic;
+use std::panic::PanicHookInfo;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use crate::{FailureKind, TestFailure};
+
+static INSTALL_HOOK: Once = Once::new();
+static FORCE_BACKTRACE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<PanicDetails>> = const { RefCell::new(None) };
+}
+
+struct PanicDetails {
+    location: Option<String>,
+    backtrace: Option<String>,
+}
+
+/// Installs a panic hook which remembers location and backtrace of the last panic of each thread.
+/// The previously installed hook is still invoked, so panics are printed as usual.
+pub(crate) fn install_panic_hook(force_backtrace: bool) {
+    FORCE_BACKTRACE.store(force_backtrace, Ordering::SeqCst);
+    INSTALL_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            record_panic(info);
+            previous_hook(info);
+        }));
+    });
+}
+
+/// Forgets the panic recorded on the current thread, should be called before a test starts
+pub(crate) fn clear_last_panic() {
+    LAST_PANIC.with(|last_panic| last_panic.borrow_mut().take());
+}
+
+/// Builds the failure from the panic payload and the details recorded by the hook on the current thread
+pub(crate) fn failure_from_panic(payload: &(dyn Any + Send)) -> TestFailure {
+    let details = LAST_PANIC.with(|last_panic| last_panic.borrow_mut().take());
+    let (location, backtrace) = match details {
+        Some(details) => (details.location, details.backtrace),
+        None => (None, None),
+    };
+    TestFailure {
+        kind: FailureKind::Panic,
+        message: panic_message(payload),
+        location,
+        backtrace,
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+fn record_panic(info: &PanicHookInfo) {
+    let backtrace = if FORCE_BACKTRACE.load(Ordering::SeqCst) {
+        Backtrace::force_capture()
+    } else {
+        Backtrace::capture()
+    };
+    let backtrace = match backtrace.status() {
+        BacktraceStatus::Captured => Some(backtrace.to_string()),
+        _ => None,
+    };
+    let details = PanicDetails {
+        location: info.location().map(|location| location.to_string()),
+        backtrace,
+    };
+    // try_with, because the thread local might be already destroyed if the thread is exiting
+    let _ = LAST_PANIC.try_with(|last_panic| *last_panic.borrow_mut() = Some(details));
+}
@@ -0,0 +1,227 @@
+//! Printing of the collected tests and of the results in the formats supported by `--format`.
+use test_collector_utils::IntegrationTestMeta;
+use crate::{FailureKind, TestFailure, TestResult, TestResults};
+use crate::args::OutputFormat;
+use crate::logger::{log_error_static_info, log_static_info};
+
+pub(crate) fn print_test_list(tests: &[&IntegrationTestMeta], format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            for test in tests {
+                let location = match &test.location {
+                    Some(location) => format!(r#", "source_path": {}, "start_line": {}, "start_col": {}"#,
+                                              json_string(&location.file), location.line, location.column),
+                    None => String::new(),
+                };
+                let suite = match &test.suite {
+                    Some(suite) => format!(r#", "suite": {}"#, json_string(suite)),
+                    None => String::new(),
+                };
+                println!(r#"{{ "type": "test", "event": "discovered", "name": {}, "kind": "{}", "ignore": {}, "ignore_message": {}, "tags": [{}]{}{} }}"#,
+                         json_string(&test.full_name()),
+                         test_kind(test),
+                         test.ignored,
+                         json_string(test.ignore_reason.as_deref().unwrap_or_default()),
+                         test.tags.iter().map(|tag| json_string(tag)).collect::<Vec<_>>().join(", "),
+                         suite,
+                         location,
+                );
+            }
+        }
+        OutputFormat::Pretty => {
+            for test in tests {
+                println!("{}: test", test.full_name());
+            }
+            println!();
+            println!("{} tests, 0 benchmarks", tests.len());
+        }
+        OutputFormat::Terse => {
+            for test in tests {
+                println!("{}: test", test.full_name());
+            }
+        }
+    }
+}
+
+pub(crate) fn print_results(result: &TestResults, format: OutputFormat) {
+    if let Some(error) = &result.start_error {
+        log_error_static_info(format_args!("Test environment failed to start: {}", error));
+    }
+    if let Some(error) = &result.stop_error {
+        log_error_static_info(format_args!("Test environment failed to stop: {}", error));
+    }
+    if result.interrupted {
+        log_error_static_info(format_args!("Test run was interrupted"));
+    }
+    if let Some(seed) = result.shuffle_seed {
+        log_static_info(format_args!("Tests were shuffled with seed {}", seed));
+    }
+    for suite in &result.suites {
+        if let Some(error) = &suite.start_error {
+            log_error_static_info(format_args!("Suite [{}] failed to start: {}", suite.name, error));
+        }
+        if let Some(error) = &suite.stop_error {
+            log_error_static_info(format_args!("Suite [{}] failed to stop: {}", suite.name, error));
+        }
+    }
+    match format {
+        OutputFormat::Pretty => print_pretty(result),
+        OutputFormat::Terse => print_terse(result),
+        OutputFormat::Json => print_json(result),
+    }
+}
+
+pub(crate) fn describe_failure(failure: &TestFailure) -> String {
+    match (&failure.location, failure.kind) {
+        (Some(location), _) => format!("panicked at {}:\n{}", location, failure.message),
+        (None, FailureKind::Panic) => format!("panicked:\n{}", failure.message),
+        (None, _) => failure.message.clone(),
+    }
+}
+
+fn test_kind(test: &IntegrationTestMeta) -> &'static str {
+    if test.is_async() {
+        "async"
+    } else {
+        "sync"
+    }
+}
+
+fn print_summary(result: &TestResults) {
+    for suite in &result.suites {
+        log_static_info(format_args!("Suite [{}]: successful tests {}. Failed tests {}. Flaky tests {}. Not run tests {}. \
+                                      Start up {:?}, tests {:?}, stop {:?}",
+                                     suite.name, suite.passed, suite.failed, suite.flaky, suite.not_run,
+                                     suite.start_up_duration, suite.tests_duration, suite.stop_duration));
+    }
+    log_static_info(format_args!("Successful test {}. Failed tests {}. Ignored tests {}. Flaky tests {}. Not run tests {}",
+                                 result.success_tests.len(),
+                                 result.failed_tests.len(),
+                                 result.ignored_tests.len(),
+                                 result.flaky_tests.len(),
+                                 result.not_run_tests.len(),
+    ));
+}
+
+fn print_pretty(result: &TestResults) {
+    print_summary(result);
+    for test in &result.success_tests {
+        log_static_info(format_args!("Test [{}] ....... PASSED", test.name));
+    }
+    for test in &result.flaky_tests {
+        log_static_info(format_args!("Test [{}] ....... FLAKY (passed on attempt {})", test.name, test.attempts));
+    }
+    for test in &result.ignored_tests {
+        log_static_info(format_args!("Test [{}] ....... IGNORED{}", test.name, reason_suffix(test)));
+    }
+    for test in &result.not_run_tests {
+        log_error_static_info(format_args!("Test [{}] ....... NOT RUN{}", test.name, reason_suffix(test)));
+    }
+    for test in &result.failed_tests {
+        log_error_static_info(format_args!("Test [{}] ....... FAILED", test.name));
+    }
+    print_failure_details(result);
+}
+
+fn print_terse(result: &TestResults) {
+    for test in &result.flaky_tests {
+        log_static_info(format_args!("Test [{}] ....... FLAKY (passed on attempt {})", test.name, test.attempts));
+    }
+    for test in &result.failed_tests {
+        log_error_static_info(format_args!("Test [{}] ....... FAILED", test.name));
+    }
+    print_failure_details(result);
+    print_summary(result);
+}
+
+fn reason_suffix(test: &TestResult) -> String {
+    match &test.skip_reason {
+        Some(reason) => format!(" ({})", reason),
+        None => String::new(),
+    }
+}
+
+fn print_failure_details(result: &TestResults) {
+    for test in &result.failed_tests {
+        if let Some(failure) = &test.failure {
+            log_error_static_info(format_args!("Test [{}] {}", test.name, describe_failure(failure)));
+            if let Some(backtrace) = &failure.backtrace {
+                log_error_static_info(format_args!("Backtrace of [{}]:\n{}", test.name, backtrace));
+            }
+        }
+        if let Some(output) = test.output.as_ref().filter(|output| !output.is_empty()) {
+            log_error_static_info(format_args!("Output of [{}]:\n{}", test.name, output));
+        }
+    }
+}
+
+/// Prints the results in the same shape as the libtest `--format json` events.
+fn print_json(result: &TestResults) {
+    for test in &result.success_tests {
+        print_json_test_event(test, "ok");
+    }
+    for test in &result.flaky_tests {
+        print_json_test_event(test, "ok");
+    }
+    // libtest has no event for tests which were not run, they are reported as ignored with the reason
+    for test in result.ignored_tests.iter().chain(&result.not_run_tests) {
+        print_json_test_event(test, "ignored");
+    }
+    for test in &result.failed_tests {
+        print_json_test_event(test, "failed");
+    }
+    let event = if result.failed_tests.is_empty() { "ok" } else { "failed" };
+    let shuffle_seed = match result.shuffle_seed {
+        Some(seed) => format!(r#", "shuffle_seed": {}"#, seed),
+        None => String::new(),
+    };
+    println!(r#"{{ "type": "suite", "event": "{}", "passed": {}, "failed": {}, "ignored": {}, "measured": 0, "filtered_out": {}, "exec_time": {}{} }}"#,
+             event,
+             result.success_tests.len() + result.flaky_tests.len(),
+             result.failed_tests.len(),
+             result.ignored_tests.len() + result.not_run_tests.len(),
+             result.filtered_out,
+             result.tests_duration.as_secs_f64(),
+             shuffle_seed,
+    );
+}
+
+fn print_json_test_event(test: &TestResult, event: &str) {
+    let message = test.failure.as_ref()
+        .map(|failure| failure.message.as_str())
+        .or(test.skip_reason.as_deref());
+    let message = match message {
+        Some(message) => format!(r#", "message": {}"#, json_string(message)),
+        None => String::new(),
+    };
+    let attempts = if test.attempts > 1 {
+        format!(r#", "attempts": {}"#, test.attempts)
+    } else {
+        String::new()
+    };
+    println!(r#"{{ "type": "test", "name": {}, "event": "{}", "exec_time": {}{}{} }}"#,
+             json_string(&test.name),
+             event,
+             test.duration.as_secs_f64(),
+             message,
+             attempts,
+    );
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,61 @@
+//! Random order of the tests which can be reproduced with the same seed.
+//! Uses its own small generator (SplitMix64), so the order does not change between versions of dependencies.
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use test_collector_utils::IntegrationTestMeta;
+
+/// Seed for a run which was not given one, different for every run
+pub(crate) fn random_seed() -> u64 {
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
+
+/// Tests are sorted by name first, so the result depends only on the seed and not on the collection order
+pub(crate) fn shuffle_tests(mut tests: Vec<&IntegrationTestMeta>, seed: u64) -> Vec<&IntegrationTestMeta> {
+    tests.sort_by_cached_key(|test| test.full_name());
+    shuffle(&mut tests, seed);
+    tests
+}
+
+/// Fisher-Yates shuffle
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut random = SplitMix64 { state: seed };
+    for index in (1..items.len()).rev() {
+        let other = (random.next() % (index as u64 + 1)) as usize;
+        items.swap(index, other);
+    }
+}
+
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shuffle::shuffle;
+
+    fn shuffled(seed: u64) -> Vec<u32> {
+        let mut items: Vec<u32> = (0..20).collect();
+        shuffle(&mut items, seed);
+        items
+    }
+
+    #[test]
+    fn same_seed_gives_same_order() {
+        assert_eq!(shuffled(42), shuffled(42));
+        assert_ne!(shuffled(42), shuffled(43));
+
+        let mut items = shuffled(42);
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<u32>>());
+    }
+}
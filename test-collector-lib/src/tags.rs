@@ -0,0 +1,170 @@
+//! Boolean expressions over the tags of the tests, e.g. `smoke and not (db or external)`.
+//! `not` binds tighter than `and`, which binds tighter than `or`.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TagExpr {
+    Tag(String),
+    Not(Box<TagExpr>),
+    And(Box<TagExpr>, Box<TagExpr>),
+    Or(Box<TagExpr>, Box<TagExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Tag(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl TagExpr {
+    pub(crate) fn parse(expression: &str) -> Result<TagExpr, String> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected {:?} in tag expression [{}]", token, expression)),
+        }
+    }
+
+    pub(crate) fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            TagExpr::Tag(tag) => tags.iter().any(|test_tag| test_tag == tag),
+            TagExpr::Not(expr) => !expr.matches(tags),
+            TagExpr::And(left, right) => left.matches(tags) && right.matches(tags),
+            TagExpr::Or(left, right) => left.matches(tags) || right.matches(tags),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c if is_tag_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(index, c)) = chars.peek() {
+                    if !is_tag_char(c) {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(match &expression[start..end] {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    tag => Token::Tag(tag.to_string()),
+                });
+            }
+            c => return Err(format!("Unexpected character '{}' in tag expression [{}]", c, expression)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':'
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next_if(&mut self, expected: &Token) -> bool {
+        if self.tokens.get(self.position) == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<TagExpr, String> {
+        let mut expr = self.and()?;
+        while self.next_if(&Token::Or) {
+            expr = TagExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<TagExpr, String> {
+        let mut expr = self.not()?;
+        while self.next_if(&Token::And) {
+            expr = TagExpr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<TagExpr, String> {
+        if self.next_if(&Token::Not) {
+            return Ok(TagExpr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<TagExpr, String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Tag(tag)) => Ok(TagExpr::Tag(tag)),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                if self.next_if(&Token::Close) {
+                    Ok(expr)
+                } else {
+                    Err("Missing closing parenthesis in tag expression".to_string())
+                }
+            }
+            Some(token) => Err(format!("Expected tag but found {:?} in tag expression", token)),
+            None => Err("Unexpected end of tag expression".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tags::TagExpr;
+
+    fn matches(expression: &str, tags: &[&str]) -> bool {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        TagExpr::parse(expression).unwrap().matches(&tags)
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        assert!(matches("smoke", &["smoke", "db"]));
+        assert!(!matches("smoke", &["db"]));
+        assert!(matches("smoke and not external", &["smoke", "db"]));
+        assert!(!matches("smoke and not external", &["smoke", "external"]));
+        assert!(matches("not smoke", &[]));
+        assert!(matches("db or external and smoke", &["db"]));
+        assert!(!matches("(db or external) and smoke", &["db"]));
+        assert!(matches("not not slow-db", &["slow-db"]));
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let tag = |name: &str| Box::new(TagExpr::Tag(name.to_string()));
+        assert_eq!(TagExpr::parse("a or b and not c").unwrap(),
+                   TagExpr::Or(tag("a"), Box::new(TagExpr::And(tag("b"), Box::new(TagExpr::Not(tag("c")))))));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(TagExpr::parse("").is_err());
+        assert!(TagExpr::parse("smoke and").is_err());
+        assert!(TagExpr::parse("(smoke or db").is_err());
+        assert!(TagExpr::parse("smoke db").is_err());
+        assert!(TagExpr::parse("smoke & db").is_err());
+    }
+}
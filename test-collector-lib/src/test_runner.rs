@@ -1,134 +1,777 @@
-use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::panic;
 use std::panic::AssertUnwindSafe;
-use std::time::Instant;
-use futures::FutureExt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use test_collector_utils::IntegrationTestMeta;
-use crate::{TestEnvironment, TestResult, TestResults};
+use crate::{FailureKind, TestEnvironment, TestFailure, TestResult, TestResults, TestSuite};
+use crate::args::{OutputFormat, RunIgnored, TestArgs};
+use crate::capture::OutputCapture;
+use crate::filter::{NamePattern, TestFilter};
+use crate::execution::{execute_async, execute_async_hook, execute_hook, execute_sync, execute_with_fixtures, expect_outcome, sync_test, ShouldPanic};
+use crate::fixtures::{async_test, EnvironmentFixtures};
+use crate::interrupt::{install_interrupt_handler, is_interrupted, reset_interruption};
+use crate::panic_hook::{install_panic_hook, panic_message};
 use crate::logger::{log_error_static_info, log_error_test, log_static_info, log_test};
+use crate::report::{describe_failure, print_results, print_test_list};
+use crate::shuffle::{random_seed, shuffle_tests};
+use crate::test_runner::ordering::{order_tests, skipped_test_result, Dependencies, DependencyState};
+use crate::test_runner::suites::group_by_suite;
+
+mod ordering;
+mod scheduler;
+mod suites;
+
+const JUNIT_SUITE_NAME: &str = "test-collector";
 
 pub struct TestRunner<T: TestEnvironment> {
     test_environment: T,
+    /// Whether `TestArgs::from_env()` is applied when the run starts
+    env_args: bool,
+    filter: TestFilter,
+    format: OutputFormat,
+    list: bool,
+    capture: bool,
+    backtrace: bool,
+    test_threads: usize,
+    async_concurrency: usize,
+    default_timeout: Option<Duration>,
+    retries: u32,
+    max_failures: Option<usize>,
+    signal_handling: bool,
+    interrupt_grace_period: Duration,
+    junit_report: Option<PathBuf>,
+    shuffle: bool,
+    shuffle_seed: Option<u64>,
+    suites: HashMap<String, RefCell<Box<dyn TestSuite<T>>>>,
+    /// Tests added with `with_test`, executed together with the collected ones
+    dynamic_tests: Vec<IntegrationTestMeta>,
+    /// Whether the finished tests passed, checked by the tests depending on them
+    outcomes: RefCell<HashMap<String, bool>>,
 }
 
 impl<T: TestEnvironment> TestRunner<T> {
     pub fn new(test_environment: T) -> TestRunner<T> {
         TestRunner {
-            test_environment
+            test_environment,
+            env_args: true,
+            filter: TestFilter::new(),
+            format: OutputFormat::Pretty,
+            list: false,
+            capture: false,
+            backtrace: false,
+            test_threads: 1,
+            async_concurrency: 1,
+            default_timeout: None,
+            retries: 0,
+            max_failures: None,
+            signal_handling: true,
+            interrupt_grace_period: Duration::from_secs(10),
+            junit_report: None,
+            shuffle: false,
+            shuffle_seed: None,
+            suites: HashMap::new(),
+            dynamic_tests: Vec::new(),
+            outcomes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Applies libtest compatible arguments instead of the ones of the current process.
+    /// Settings which the arguments do not specify are kept, e.g. the ones of `with_list` or `with_run_ignored`.
+    /// Output is not captured by default, so `--nocapture` only turns off `with_output_capture`.
+    pub fn with_args(mut self, args: TestArgs) -> TestRunner<T> {
+        self.env_args = false;
+        self.filter.apply_args(&args);
+        if args.format != OutputFormat::Pretty {
+            self.format = args.format;
+        }
+        self.list |= args.list;
+        if args.nocapture {
+            self.capture = false;
+        }
+        if let Some(test_threads) = args.test_threads {
+            self.test_threads = test_threads;
+        }
+        self.shuffle |= args.shuffle || args.shuffle_seed.is_some();
+        if args.shuffle_seed.is_some() {
+            self.shuffle_seed = args.shuffle_seed;
+        }
+        self
+    }
+
+    /// Applies the arguments of the current process with `TestArgs::from_env()` when the run starts, enabled by default,
+    /// so `cargo test --test integration -- some_name --exact` runs only the matching tests.
+    /// Disable it if the arguments of the process are not meant for the runner, e.g. when it is started from a `#[test]`.
+    pub fn with_env_args(mut self, env_args: bool) -> TestRunner<T> {
+        self.env_args = env_args;
+        self
+    }
+
+    /// Runs sync tests concurrently on the given number of threads, tests are executed one by one by default.
+    /// `before_each_test` and `after_each_test` are still invoked for every test on the current thread,
+    /// so the environment does not need to be thread safe, but hooks of different tests can interleave.
+    /// Async tests are executed on the current thread, one by one unless `with_async_concurrency` is used as well.
+    /// Tests marked with `#[collect_test(serial = "group")]` never overlap with the tests of the same group.
+    pub fn with_test_threads(mut self, test_threads: usize) -> TestRunner<T> {
+        assert!(test_threads > 0, "Number of test threads must be greater than 0");
+        self.test_threads = test_threads;
+        self
+    }
+
+    /// Polls up to the given number of async tests at the same time inside a single `block_on`,
+    /// async tests are executed one by one by default.
+    /// Tests are started in their order, `before_each_test` is invoked right before
+    /// the test is started and `after_each_test` right after it finished.
+    /// The hooks are invoked inside that `block_on` as well, so they must not call it themselves.
+    pub fn with_async_concurrency(mut self, async_concurrency: usize) -> TestRunner<T> {
+        assert!(async_concurrency > 0, "Async concurrency must be greater than 0");
+        self.async_concurrency = async_concurrency;
+        self
+    }
+
+    /// Captures stdout of each test into `TestResult::output`, disabled by default.
+    /// Stdout is redirected for the whole process while a test is running.
+    pub fn with_output_capture(mut self, capture: bool) -> TestRunner<T> {
+        self.capture = capture;
+        self
+    }
+
+    /// Timeout for tests which do not specify their own with `#[collect_test(timeout = "30s")]`.
+    /// A timed out test is reported as failed with `FailureKind::Timeout` and the runner continues with the next one.
+    /// Async tests are dropped on timeout, while sync tests are left running on their own thread.
+    /// Sync tests taking fixtures can not be abandoned, so the default timeout does not apply to them.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> TestRunner<T> {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Executes failed tests again up to the given number of times, unless they specify their own
+    /// with `#[collect_test(retries = 3)]`. Hooks are invoked for every attempt.
+    /// Tests which pass only after a retry are reported in `TestResults::flaky_tests`.
+    pub fn with_retries(mut self, retries: u32) -> TestRunner<T> {
+        self.retries = retries;
+        self
+    }
+
+    /// Stops running tests after the first failed one, same as `with_max_failures(1)`.
+    pub fn with_fail_fast(self) -> TestRunner<T> {
+        self.with_max_failures(1)
+    }
+
+    /// Stops running tests after the given number of failed tests, the remaining tests are
+    /// reported in `TestResults::not_run_tests`. The environment is still stopped as usual.
+    /// Tests which are already running when the limit is reached are allowed to finish.
+    pub fn with_max_failures(mut self, max_failures: usize) -> TestRunner<T> {
+        assert!(max_failures > 0, "Maximum number of failures must be greater than 0");
+        self.max_failures = Some(max_failures);
+        self
+    }
+
+    /// Handles SIGINT (Ctrl-C) and SIGTERM, enabled by default. On the first signal no more tests are started,
+    /// the remaining ones are reported in `TestResults::not_run_tests` and the environment is stopped as usual.
+    /// The second signal exits the process right away. Has no effect if the application has its own handler.
+    pub fn with_signal_handling(mut self, signal_handling: bool) -> TestRunner<T> {
+        self.signal_handling = signal_handling;
+        self
+    }
+
+    /// How long the running tests can take after the run was interrupted, 10 seconds by default.
+    /// Tests which are still running after it are reported with `FailureKind::Interrupted`
+    /// and the environment is stopped without waiting for them.
+    /// Sync tests without a timeout are executed on the current thread and can not be abandoned,
+    /// they are waited for, unless the second signal exits the process.
+    pub fn with_interrupt_grace_period(mut self, grace_period: Duration) -> TestRunner<T> {
+        self.interrupt_grace_period = grace_period;
+        self
+    }
+
+    /// Always captures backtraces of failed tests, otherwise they are captured only if `RUST_BACKTRACE` is set.
+    pub fn with_backtrace(mut self, backtrace: bool) -> TestRunner<T> {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// Writes JUnit XML report of the results to the given path after the environment is stopped.
+    pub fn with_junit_report<P: Into<PathBuf>>(mut self, path: P) -> TestRunner<T> {
+        self.junit_report = Some(path.into());
+        self
+    }
+
+    /// Executes the tests in random order to find tests which depend on each other through the environment.
+    /// The seed is printed at start up and recorded in `TestResults::shuffle_seed`.
+    /// Suites, explicit orders and dependencies are still respected.
+    pub fn with_shuffle(mut self) -> TestRunner<T> {
+        self.shuffle = true;
+        self
+    }
+
+    /// Executes the tests in the random order given by the seed, same as `--shuffle-seed`,
+    /// so the order of a previous run can be reproduced.
+    pub fn with_shuffle_seed(mut self, seed: u64) -> TestRunner<T> {
+        self.shuffle = true;
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Registers the set up and tear down of the tests marked with `#[collect_test(suite = "billing")]`.
+    /// Tests of a suite are executed together, tests without a suite first, then the suites one after another.
+    /// Suites which are not registered are still executed together and reported in `TestResults::suites`.
+    /// The run panics before the start if none of the tests belongs to a registered suite.
+    pub fn with_suite<S: TestSuite<T> + 'static>(mut self, name: &str, suite: S) -> TestRunner<T> {
+        self.suites.insert(name.to_string(), RefCell::new(Box::new(suite)));
+        self
+    }
+
+    /// Adds a test created at runtime, e.g. one per data file, with `IntegrationTestMeta::for_sync_closure`
+    /// or `IntegrationTestMeta::for_async_closure`. It is filtered, ordered and reported like the tests
+    /// collected by `#[collect_test]`. Panics if a test with the same full name is already registered.
+    pub fn with_test(mut self, test: IntegrationTestMeta) -> TestRunner<T> {
+        let name = test.full_name();
+        if all_tests(&self.dynamic_tests).any(|other| other.full_name() == name) {
+            panic!("Test [{}] is already registered", name);
         }
+        self.dynamic_tests.push(test);
+        self
     }
 
+    /// Adds several tests created at runtime, see `with_test`.
+    pub fn with_tests<I: IntoIterator<Item=IntegrationTestMeta>>(self, tests: I) -> TestRunner<T> {
+        tests.into_iter().fold(self, TestRunner::with_test)
+    }
+
+    /// Decides whether tests marked with `#[collect_test(ignore)]` are executed, same as `--ignored` and `--include-ignored`.
+    pub fn with_run_ignored(mut self, run_ignored: RunIgnored) -> TestRunner<T> {
+        self.filter.set_run_ignored(run_ignored);
+        self
+    }
+
+    /// Runs only tests which names contain the given substring.
+    /// Can be called multiple times, a test is executed if it matches any of the filters.
+    pub fn filter(mut self, substring: &str) -> TestRunner<T> {
+        self.filter.include(NamePattern::Substring(substring.to_string()));
+        self
+    }
+
+    /// Runs the test with exactly the given name.
+    pub fn filter_exact(mut self, name: &str) -> TestRunner<T> {
+        self.filter.include(NamePattern::Exact(name.to_string()));
+        self
+    }
+
+    /// Runs only tests which names match the given regex, panics if the regex is invalid.
+    pub fn filter_regex(mut self, regex: &str) -> TestRunner<T> {
+        self.filter.include(NamePattern::regex(regex));
+        self
+    }
+
+    /// Skips tests which names contain the given substring, even if they match one of the filters.
+    pub fn skip(mut self, substring: &str) -> TestRunner<T> {
+        self.filter.skip(NamePattern::Substring(substring.to_string()));
+        self
+    }
+
+    /// Skips tests which names match the given regex, panics if the regex is invalid.
+    pub fn skip_regex(mut self, regex: &str) -> TestRunner<T> {
+        self.filter.skip(NamePattern::regex(regex));
+        self
+    }
+
+    /// Runs only tests which tags match the boolean expression, e.g. `smoke and not (db or external)`,
+    /// panics if the expression is invalid. Same as `--tags`, multiple expressions must all match.
+    pub fn with_tags(mut self, expression: &str) -> TestRunner<T> {
+        self.filter.tags(expression);
+        self
+    }
+
+    /// Only lists the tests which would be executed instead of running them,
+    /// test environment is not started in this mode.
+    /// Uses the libtest `name: test` format or JSON lines if the json format was requested.
+    pub fn with_list(mut self) -> TestRunner<T> {
+        self.list = true;
+        self
+    }
+
+    fn apply_env_args(self) -> TestRunner<T> {
+        if self.env_args {
+            self.with_args(TestArgs::from_env())
+        } else {
+            self
+        }
+    }
+}
+
+/// Running requires a `'static` environment, since tests can take a reference to it as a fixture
+impl<T: TestEnvironment + 'static> TestRunner<T> {
     pub fn run_safe(mut self) -> TestResults {
+        self = self.apply_env_args();
+        // taken out of the runner, so the tests can be borrowed while the environment is moved around
+        let dynamic_tests = std::mem::take(&mut self.dynamic_tests);
+        if self.list {
+            self.list_tests(&dynamic_tests);
+            return TestResults::empty(self.filtered_out_count(&dynamic_tests));
+        }
+        reset_interruption();
+        if self.signal_handling {
+            install_interrupt_handler(self.interrupt_grace_period);
+        }
+        // checked, selected and ordered before the start, so invalid suites and dependencies panic
+        // before anything has to be stopped
+        self.check_suites(&dynamic_tests);
+        let mut results = TestResults::empty(self.filtered_out_count(&dynamic_tests));
+        let mut tests = self.tests_to_run(&dynamic_tests, &mut results);
+        if self.shuffle {
+            let seed = self.shuffle_seed.unwrap_or_else(random_seed);
+            log_static_info(format_args!("Tests are shuffled with seed {}, pass `--shuffle-seed {}` to get the same order", seed, seed));
+            tests = shuffle_tests(tests, seed);
+            results.shuffle_seed = Some(seed);
+        }
+        let known_tests: HashSet<String> = all_tests(&dynamic_tests).map(|test| test.full_name()).collect();
+        let tests = order_tests(group_by_suite(tests), &known_tests);
         log_static_info(format_args!("Next step is to start test environment"));
         let spin_up_started_at = Instant::now();
-        self.test_environment = self.test_environment.start();
+        let start_error = match self.test_environment.try_start() {
+            Ok(environment) => {
+                self.test_environment = environment;
+                // a panic is a start error as well, so the environment is still stopped
+                let started = panic::catch_unwind(AssertUnwindSafe(|| {
+                    self.test_environment.block_on(self.test_environment.start_async())
+                }));
+                match started {
+                    Ok(result) => result.err().map(|e| e.to_string()),
+                    Err(e) => Some(format!("start_async panicked: {}", panic_message(e.as_ref()))),
+                }
+            }
+            Err(e) => {
+                self.test_environment = e.environment;
+                Some(e.error.to_string())
+            }
+        };
         let start_up_duration = spin_up_started_at.elapsed();
-        log_static_info(format_args!("Test environment was started within {:?}", start_up_duration));
+        match &start_error {
+            None => log_static_info(format_args!("Test environment was started within {:?}", start_up_duration)),
+            Some(error) => log_error_static_info(format_args!("Test environment failed to start within {:?}: {}",
+                                                              start_up_duration, error)),
+        }
         log_static_info(format_args!("***"));
 
+        if self.capture && (self.test_threads > 1 || self.async_concurrency > 1) {
+            log_error_static_info(format_args!("Output is not captured when tests are executed concurrently"));
+            self.capture = false;
+        }
         log_static_info(format_args!("Next step is to run tests"));
         let tests_started_at = Instant::now();
-        let (success_tests, failed_tests) = self.run_tests();
+        match &start_error {
+            None => self.run_tests(&tests, &known_tests, &mut results),
+            Some(error) => self.fail_tests_by_setup(&tests, &mut results, error),
+        }
+        results.start_error = start_error;
         let tests_duration = tests_started_at.elapsed();
         log_static_info(format_args!("All tests finished within {:?}", tests_duration));
         log_static_info(format_args!("***"));
 
         log_static_info(format_args!("Next step is to stop test environment"));
         let teardown_started_at = Instant::now();
-        self.test_environment = self.test_environment.stop();
+        let environment = self.test_environment;
+        // caught separately, so `try_stop` is invoked even if `stop_async` panicked
+        let async_error = match panic::catch_unwind(AssertUnwindSafe(|| environment.block_on(environment.stop_async()))) {
+            Ok(result) => result.err().map(|e| e.to_string()),
+            Err(e) => Some(format!("stop_async panicked: {}", panic_message(e.as_ref()))),
+        };
+        let stop_error = match panic::catch_unwind(AssertUnwindSafe(move || environment.try_stop())) {
+            Ok(result) => result.err().map(|e| e.error.to_string()),
+            Err(e) => Some(format!("stop panicked: {}", panic_message(e.as_ref()))),
+        };
+        let errors: Vec<String> = async_error.into_iter().chain(stop_error).collect();
+        if !errors.is_empty() {
+            results.stop_error = Some(errors.join(", "));
+        }
         let stop_duration = teardown_started_at.elapsed();
-        log_static_info(format_args!("Test environment was stopped, within {:?}", stop_duration));
+        match &results.stop_error {
+            None => log_static_info(format_args!("Test environment was stopped, within {:?}", stop_duration)),
+            Some(error) => log_error_static_info(format_args!("Test environment failed to stop within {:?}: {}",
+                                                              stop_duration, error)),
+        }
         log_static_info(format_args!("***"));
 
         let overall_duration = spin_up_started_at.elapsed();
         log_static_info(format_args!("Overall duration {:?}", overall_duration));
 
-        return TestResults {
-            success_tests,
-            failed_tests,
-            start_up_duration,
-            tests_duration,
-            stop_duration,
-        };
+        results.start_up_duration = start_up_duration;
+        results.tests_duration = tests_duration;
+        results.stop_duration = stop_duration;
+        if let Some(path) = &self.junit_report {
+            match results.write_junit_xml(JUNIT_SUITE_NAME, path) {
+                Ok(_) => log_static_info(format_args!("JUnit report was written to {}", path.display())),
+                Err(e) => log_error_static_info(format_args!("Failed to write JUnit report to {}: {}", path.display(), e)),
+            }
+        }
+        results
     }
 
-    pub fn run(self) {
+    pub fn run(mut self) {
+        self = self.apply_env_args();
+        if self.list {
+            self.run_safe();
+            return;
+        }
+        let format = self.format;
         let result = self.run_safe();
-        let success_test_number = result.success_tests.len();
-        let failed_test_number =result.failed_tests.len();
-        log_static_info(format_args!("Successful test {}. Failed tests {}",
-                                     success_test_number,
-                                     failed_test_number,
-        ));
-        for test in result.success_tests {
-            log_static_info(format_args!("Test [{}] ....... PASSED", test.name));
-        }
-        for test in result.failed_tests {
-            log_error_static_info(format_args!("Test [{}] ....... FAILED", test.name));
+        let failed_test_number = result.failed_tests.len();
+        print_results(&result, format);
+        if result.interrupted {
+            panic!("Test run was interrupted");
         }
         if failed_test_number > 0 {
             panic!("Some tests are Failing");
         }
+        // also when no test was selected, so none of them failed by setup
+        if result.start_error.is_some() {
+            panic!("Test environment failed to start");
+        }
+        if result.stop_error.is_some() {
+            panic!("Test environment failed to stop");
+        }
+        if result.suites.iter().any(|suite| suite.stop_error.is_some()) {
+            panic!("Test suite failed to stop");
+        }
+    }
+
+    fn list_tests(&self, dynamic_tests: &[IntegrationTestMeta]) {
+        print_test_list(&self.collect_tests(dynamic_tests), self.format);
     }
 
-    fn run_tests(&self) -> (Vec<TestResult>, Vec<TestResult>) {
-        let number_of_tests = inventory::iter::<IntegrationTestMeta>.into_iter().count();
-        log_static_info(format_args!("Found {} tests", number_of_tests));
-        let mut successful_tests: Vec<TestResult> = Vec::new();
-        let mut failed_tests: Vec<TestResult> = Vec::new();
-        for test in inventory::iter::<IntegrationTestMeta> {
-            log_test(format_args!("Running Before Each Test for: [{}]", test.name));
-            self.test_environment.before_each_test();
-            let result = self.run_test(test);
-            if result.success {
-                successful_tests.push(result);
+    fn collect_tests<'a>(&self, dynamic_tests: &'a [IntegrationTestMeta]) -> Vec<&'a IntegrationTestMeta> {
+        all_tests(dynamic_tests)
+            .filter(|test| self.filter.matches(test))
+            .collect()
+    }
+
+    fn filtered_out_count(&self, dynamic_tests: &[IntegrationTestMeta]) -> usize {
+        all_tests(dynamic_tests)
+            .filter(|test| !self.filter.matches(test))
+            .count()
+    }
+
+    /// Reports the ignored tests and returns the ones which should be executed
+    fn tests_to_run<'a>(&self, dynamic_tests: &'a [IntegrationTestMeta], results: &mut TestResults) -> Vec<&'a IntegrationTestMeta> {
+        let (ignored, tests): (Vec<_>, Vec<_>) = self.collect_tests(dynamic_tests).into_iter()
+            .partition(|test| test.ignored && self.filter.run_ignored() == RunIgnored::No);
+        log_static_info(format_args!("Found {} tests, {} ignored, {} filtered out",
+                                     tests.len(), ignored.len(), results.filtered_out));
+        results.ignored_tests.extend(ignored.into_iter().map(ignored_test_result));
+        tests
+    }
+
+    fn fail_tests_by_setup(&self, tests: &[&IntegrationTestMeta], results: &mut TestResults, start_error: &str) {
+        let message = format!("Test environment failed to start: {}", start_error);
+        results.failed_tests.extend(tests.iter().map(|test| setup_failed_test_result(test, &message)));
+    }
+
+    fn run_tests(&self, tests: &[&IntegrationTestMeta], known_tests: &HashSet<String>, results: &mut TestResults) {
+        install_panic_hook(self.backtrace);
+        let mut executed: Vec<Option<TestResult>> = Vec::with_capacity(tests.len());
+        for suite_tests in tests.chunk_by(|first, second| first.suite == second.suite) {
+            let failed_before = executed.iter().flatten().filter(|result| result.failure.is_some()).count();
+            match &suite_tests[0].suite {
+                None => executed.extend(self.execute(suite_tests, known_tests, failed_before)),
+                Some(suite) => {
+                    let (suite_executed, suite_results) = self.run_suite(suite, suite_tests, known_tests, failed_before);
+                    executed.extend(suite_executed);
+                    results.suites.push(suite_results);
+                }
+            }
+        }
+        results.interrupted = is_interrupted();
+        let not_run_reason = if results.interrupted {
+            "not run, the run was interrupted".to_string()
+        } else {
+            format!("not run, tests were stopped after {} failed tests", self.max_failures.unwrap_or_default())
+        };
+        if executed.iter().any(Option::is_none) {
+            log_error_static_info(format_args!("Tests were stopped, remaining tests are {}", not_run_reason));
+        }
+        for (test, result) in tests.iter().zip(executed) {
+            if let Some(suite) = results.suites.iter_mut().find(|suite| test.suite.as_ref() == Some(&suite.name)) {
+                suite.count(result.as_ref());
+            }
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    results.not_run_tests.push(not_run_test_result(test, &not_run_reason));
+                    continue;
+                }
+            };
+            if result.skip_reason.is_some() {
+                results.not_run_tests.push(result);
+            } else if !result.success {
+                results.failed_tests.push(result);
+            } else if result.attempts > 1 {
+                results.flaky_tests.push(result);
             } else {
-                failed_tests.push(result);
+                results.success_tests.push(result);
             }
-            log_test(format_args!("Running After Each Test for: [{}]", test.name));
-            self.test_environment.after_each_test();
         }
-        (successful_tests, failed_tests)
     }
 
-    fn run_test(&self, test: &IntegrationTestMeta) -> TestResult {
-        let test_started = Instant::now();
-        log_test(format_args!("Running Test: [{}]", test.name));
-        let result = self.run_test_safe(test);
-        let test_duration = test_started.elapsed();
-        let success = match result {
-            Ok(_) => {
-                log_test(format_args!("Test [{}] PASSED. Duration {:?}", test.name, test_duration));
-                true
-            }
-            Err(e) => {
-                let error: Result<Box<&'static str>, Box<dyn Any + Send>> = e.downcast();
-                if error.is_ok() {
-                    let error: Box<&'static str> = error.unwrap();
-                    log_error_test(format_args!("Test [{}] finished with ERROR. Duration {:?} \n {:?}",
-                                                test.name, test_duration, error));
-                } else {
-                    log_error_test(format_args!("Test [{}] FAILED. Duration {:?}",
-                                                test.name, test_duration));
+    /// Executes the tests one by one or concurrently, `failed_before` counts towards the fail-fast limit.
+    /// Tests whose dependencies did not pass are skipped.
+    fn execute(&self,
+               tests: &[&IntegrationTestMeta],
+               known_tests: &HashSet<String>,
+               failed_before: usize) -> Vec<Option<TestResult>> {
+        let dependencies = Dependencies::new(tests, known_tests);
+        if self.async_concurrency > 1 {
+            return self.test_environment.block_on(self.run_tests_concurrently(tests, &dependencies, failed_before));
+        }
+        if self.test_threads > 1 {
+            return self.run_tests_in_parallel(tests, &dependencies, failed_before);
+        }
+        let mut failures = failed_before;
+        tests.iter()
+            .enumerate()
+            .map(|(index, test)| {
+                if self.run_stopped(failures) {
+                    return None;
+                }
+                let result = match self.dependency_state(index, &dependencies) {
+                    DependencyState::Failed(dependency) => skipped_test_result(test, &dependency),
+                    _ => self.run_test_sequentially(test),
+                };
+                self.record_outcome(&result);
+                if result.failure.is_some() {
+                    failures += 1;
                 }
+                Some(result)
+            })
+            .collect()
+    }
+
+    /// Only the async hooks and async tests are driven by `block_on` of the environment,
+    /// so the sync hooks and tests can call it themselves
+    fn run_test_sequentially(&self, test: &IntegrationTestMeta) -> TestResult {
+        let name = test.full_name();
+        let mut attempt = 1;
+        loop {
+            let mut result = match self.before_each_blocking(test, &name) {
+                Ok(_) => self.run_test(test, &name),
+                Err(failure) => to_test_result(&name, Duration::ZERO, Err(failure), None),
+            };
+            result.attempts = attempt;
+            let after_each = self.after_each_blocking(test, &result);
+            fail_by_hook(&mut result, after_each);
+            if !self.should_retry(test, &result) {
+                return result;
+            }
+            attempt += 1;
+        }
+    }
 
-                false
+    /// Used by the concurrent scheduler for sync tests, its `block_on` drives the async hooks
+    pub(crate) async fn run_test_with_hooks(&self, test: &IntegrationTestMeta) -> TestResult {
+        let name = test.full_name();
+        let mut attempt = 1;
+        loop {
+            let mut result = match self.before_each(test, &name).await {
+                Ok(_) => self.run_test(test, &name),
+                Err(failure) => to_test_result(&name, Duration::ZERO, Err(failure), None),
+            };
+            result.attempts = attempt;
+            let after_each = self.after_each(test, &result).await;
+            fail_by_hook(&mut result, after_each);
+            if !self.should_retry(test, &result) {
+                return result;
             }
+            attempt += 1;
+        }
+    }
+
+    /// No more tests are started once the fail-fast limit is reached or the run is interrupted
+    pub(crate) fn run_stopped(&self, failures: usize) -> bool {
+        self.max_failures.is_some_and(|max_failures| failures >= max_failures) || is_interrupted()
+    }
+
+    /// Logs the retry if the failed test has attempts left
+    pub(crate) fn should_retry(&self, test: &IntegrationTestMeta, result: &TestResult) -> bool {
+        let max_attempts = test.retries.unwrap_or(self.retries) + 1;
+        if result.success || result.attempts >= max_attempts {
+            return false;
+        }
+        log_error_test(format_args!("Test [{}] failed on attempt {} of {}, retrying",
+                                    result.name, result.attempts, max_attempts));
+        true
+    }
+
+    /// Async, so the concurrent scheduler can await it inside its own `block_on`
+    pub(crate) async fn before_each(&self, test: &IntegrationTestMeta, name: &str) -> Result<(), TestFailure> {
+        log_test(format_args!("Running Before Each Test for: [{}]", name));
+        self.sync_before_each(test)?;
+        execute_async_hook("before_each_test_async", self.test_environment.before_each_test_async(test)).await
+    }
+
+    fn before_each_blocking(&self, test: &IntegrationTestMeta, name: &str) -> Result<(), TestFailure> {
+        log_test(format_args!("Running Before Each Test for: [{}]", name));
+        self.sync_before_each(test)?;
+        self.test_environment.block_on(execute_async_hook("before_each_test_async",
+                                                          self.test_environment.before_each_test_async(test)))
+    }
+
+    fn sync_before_each(&self, test: &IntegrationTestMeta) -> Result<(), TestFailure> {
+        execute_hook("before_each_test", || self.test_environment.before_each_test(test))
+    }
+
+    /// Both hooks are invoked even if the async one panicked
+    pub(crate) async fn after_each(&self, test: &IntegrationTestMeta, result: &TestResult) -> Result<(), TestFailure> {
+        log_test(format_args!("Running After Each Test for: [{}]", result.name));
+        let async_result = execute_async_hook("after_each_test_async",
+                                              self.test_environment.after_each_test_async(test, result)).await;
+        async_result.and(self.sync_after_each(test, result))
+    }
+
+    fn after_each_blocking(&self, test: &IntegrationTestMeta, result: &TestResult) -> Result<(), TestFailure> {
+        log_test(format_args!("Running After Each Test for: [{}]", result.name));
+        let async_result = self.test_environment.block_on(execute_async_hook("after_each_test_async",
+                                                                             self.test_environment.after_each_test_async(test, result)));
+        async_result.and(self.sync_after_each(test, result))
+    }
+
+    fn sync_after_each(&self, test: &IntegrationTestMeta, result: &TestResult) -> Result<(), TestFailure> {
+        execute_hook("after_each_test", || self.test_environment.after_each_test(test, result))
+    }
+
+    fn run_test(&self, test: &IntegrationTestMeta, name: &str) -> TestResult {
+        let test_started = Instant::now();
+        log_test(format_args!("Running Test: [{}]", name));
+        let capture = if self.capture { OutputCapture::start() } else { None };
+        let result = self.run_test_safe(test);
+        let output = capture.map(OutputCapture::finish);
+        to_test_result(name, test_started.elapsed(), result, output)
+    }
+
+    /// Sync tests are executed right here, only async tests are driven by `block_on` of the environment,
+    /// so it must not be called for them inside of another `block_on`
+    fn run_test_safe(&self, test: &IntegrationTestMeta) -> Result<(), TestFailure> {
+        let timeout = self.timeout_of(test);
+        let fixtures = EnvironmentFixtures::new(&self.test_environment);
+        let result = match (sync_test(test), test.fixture_fn) {
+            (Some(sync_fn), _) => execute_sync(sync_fn, timeout),
+            (_, Some(fixture_fn)) => execute_with_fixtures(fixture_fn, &fixtures),
+            _ => self.test_environment.block_on(execute_async(async_test(test, &fixtures), timeout)),
         };
-        TestResult {
-            name: test.name.clone(),
-            success,
-            duration: test_duration,
+        expect_outcome(ShouldPanic::of(test).as_ref(), result)
+    }
+
+    pub(crate) fn timeout_of(&self, test: &IntegrationTestMeta) -> Option<Duration> {
+        match test.fixture_fn {
+            Some(_) => None,
+            None => test.timeout.or(self.default_timeout),
         }
     }
+}
 
-    fn run_test_safe(&self, test: &IntegrationTestMeta) -> Result<(), Box<dyn Any + Send>> {
-        if test.sync_fn.is_some() {
-            panic::catch_unwind(test.sync_fn.unwrap())
-        } else {
-            let async_test = (test.async_fn.as_ref().unwrap())();
-            let catch_panic_wrapper = AssertUnwindSafe(async_test).catch_unwind();
-            self.test_environment.block_on(catch_panic_wrapper)
+/// Tests collected by `#[collect_test]` followed by the ones added at runtime
+fn all_tests(dynamic_tests: &[IntegrationTestMeta]) -> impl Iterator<Item=&IntegrationTestMeta> {
+    inventory::iter::<IntegrationTestMeta>.into_iter()
+        .map(|test| -> &IntegrationTestMeta { test })
+        .chain(dynamic_tests)
+}
+
+/// Logs the outcome of the test
+pub(crate) fn to_test_result(name: &str,
+                             test_duration: Duration,
+                             result: Result<(), TestFailure>,
+                             output: Option<String>) -> TestResult {
+    let (success, failure) = match result {
+        Ok(_) => {
+            log_test(format_args!("Test [{}] PASSED. Duration {:?}", name, test_duration));
+            (true, None)
+        }
+        Err(failure) => {
+            log_error_test(format_args!("Test [{}] FAILED. Duration {:?} \n {}",
+                                        name, test_duration, describe_failure(&failure)));
+            (false, Some(failure))
+        }
+    };
+    TestResult {
+        name: name.to_string(),
+        success,
+        duration: test_duration,
+        output,
+        failure,
+        skip_reason: None,
+        attempts: 1,
+    }
+}
+
+fn ignored_test_result(test: &IntegrationTestMeta) -> TestResult {
+    let name = test.full_name();
+    log_test(format_args!("Test [{}] IGNORED", name));
+    TestResult {
+        name,
+        success: false,
+        duration: Duration::ZERO,
+        output: None,
+        failure: None,
+        skip_reason: test.ignore_reason.clone(),
+        attempts: 0,
+    }
+}
+
+/// Fails the test if the hook failed, the failure of the test itself is kept if there is one
+pub(crate) fn fail_by_hook(result: &mut TestResult, hook_result: Result<(), TestFailure>) {
+    if let (true, Err(failure)) = (result.success, hook_result) {
+        log_error_test(format_args!("Test [{}] FAILED. {}", result.name, describe_failure(&failure)));
+        result.success = false;
+        result.failure = Some(failure);
+    }
+}
+
+pub(crate) fn setup_failed_test_result(test: &IntegrationTestMeta, message: &str) -> TestResult {
+    TestResult {
+        name: test.full_name(),
+        success: false,
+        duration: Duration::ZERO,
+        output: None,
+        failure: Some(TestFailure {
+            kind: FailureKind::Setup,
+            message: message.to_string(),
+            location: None,
+            backtrace: None,
+        }),
+        skip_reason: None,
+        attempts: 0,
+    }
+}
+
+fn not_run_test_result(test: &IntegrationTestMeta, reason: &str) -> TestResult {
+    TestResult {
+        name: test.full_name(),
+        success: false,
+        duration: Duration::ZERO,
+        output: None,
+        failure: None,
+        skip_reason: Some(reason.to_string()),
+        attempts: 0,
+    }
+}
+
+impl TestResults {
+    fn empty(filtered_out: usize) -> TestResults {
+        TestResults {
+            success_tests: Vec::new(),
+            failed_tests: Vec::new(),
+            ignored_tests: Vec::new(),
+            flaky_tests: Vec::new(),
+            not_run_tests: Vec::new(),
+            filtered_out,
+            start_error: None,
+            stop_error: None,
+            interrupted: false,
+            suites: Vec::new(),
+            shuffle_seed: None,
+            start_up_duration: Duration::ZERO,
+            tests_duration: Duration::ZERO,
+            stop_duration: Duration::ZERO,
         }
     }
-}
\ No newline at end of file
+}
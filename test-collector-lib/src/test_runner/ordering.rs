@@ -0,0 +1,165 @@
+//! Explicit order of the tests, `#[collect_test(order = 1)]`, and dependencies between them,
+//! `#[collect_test(depends_on = "create_account")]`. Tests are skipped if one of their dependencies failed.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::Duration;
+use test_collector_utils::IntegrationTestMeta;
+use crate::{TestEnvironment, TestResult};
+use crate::logger::log_error_test;
+use crate::test_runner::TestRunner;
+
+pub(crate) enum DependencyState {
+    Ready,
+    /// A dependency of the same run has not finished yet
+    Waiting,
+    /// Name of the dependency which failed or was skipped itself
+    Failed(String),
+}
+
+/// Dependencies of the tests executed together, resolved once, so checking them does not allocate
+pub(crate) struct Dependencies {
+    /// Full names of the dependencies of every test and whether they are executed together with it
+    of_tests: Vec<Vec<(String, bool)>>,
+}
+
+impl Dependencies {
+    /// `known_tests` are the full names of all the known tests, including the ones which are not executed
+    pub(crate) fn new(tests: &[&IntegrationTestMeta], known_tests: &HashSet<String>) -> Dependencies {
+        let names: HashSet<String> = tests.iter().map(|test| test.full_name()).collect();
+        let of_tests = tests.iter()
+            .map(|test| dependencies_of(test, known_tests).into_iter()
+                .map(|dependency| {
+                    let executed_together = names.contains(&dependency);
+                    (dependency, executed_together)
+                })
+                .collect())
+            .collect();
+        Dependencies { of_tests }
+    }
+}
+
+impl<T: TestEnvironment> TestRunner<T> {
+    /// Dependencies which are not part of the run, e.g. filtered out, are not checked.
+    /// `index` is the index of the test among the tests the dependencies were resolved for.
+    pub(crate) fn dependency_state(&self, index: usize, dependencies: &Dependencies) -> DependencyState {
+        let outcomes = self.outcomes.borrow();
+        let mut state = DependencyState::Ready;
+        for (dependency, executed_together) in &dependencies.of_tests[index] {
+            match outcomes.get(dependency) {
+                Some(true) => {}
+                Some(false) => return DependencyState::Failed(dependency.clone()),
+                None if *executed_together => state = DependencyState::Waiting,
+                None => {}
+            }
+        }
+        state
+    }
+
+    /// Records the final result of the test, after all its attempts, for the tests depending on it
+    pub(crate) fn record_outcome(&self, result: &TestResult) {
+        self.outcomes.borrow_mut().insert(result.name.clone(), result.success);
+    }
+}
+
+/// Full names of the tests the given one depends on. Dependencies are relative to the module of the test,
+/// e.g. `check_endpoint::case_1`, unless only the name as written is a known test, e.g. `accounts::create_account`.
+fn dependencies_of(test: &IntegrationTestMeta, known_tests: &HashSet<String>) -> Vec<String> {
+    test.depends_on.iter()
+        .map(|dependency| {
+            if test.module_path.is_empty() {
+                return dependency.clone();
+            }
+            let relative = format!("{}::{}", test.module_path, dependency);
+            if known_tests.contains(&relative) || !dependency.contains("::") {
+                relative
+            } else {
+                dependency.clone()
+            }
+        })
+        .collect()
+}
+
+/// Orders the tests of every suite (tests have to be grouped by suite already) by their order,
+/// tests without one last, then moves the tests after their dependencies, keeping the order otherwise.
+/// Panics if a dependency does not exist, dependencies are cyclic or a test depends on a test of a later suite.
+/// `known_tests` are the full names of all the known tests, including the ones which are not executed.
+pub(super) fn order_tests<'a>(tests: Vec<&'a IntegrationTestMeta>, known_tests: &HashSet<String>) -> Vec<&'a IntegrationTestMeta> {
+    let mut ordered: Vec<&IntegrationTestMeta> = Vec::with_capacity(tests.len());
+    for suite_tests in tests.chunk_by(|first, second| first.suite == second.suite) {
+        ordered.extend(order_suite(suite_tests, known_tests));
+    }
+    let positions: HashMap<String, usize> = ordered.iter()
+        .enumerate()
+        .map(|(position, test)| (test.full_name(), position))
+        .collect();
+    for (index, test) in ordered.iter().enumerate() {
+        for dependency in dependencies_of(test, known_tests) {
+            if !known_tests.contains(&dependency) {
+                panic!("Test [{}] depends on unknown test [{}]", test.full_name(), dependency);
+            }
+            if positions.get(&dependency).is_some_and(|position| *position >= index) {
+                panic!("Test [{}] depends on [{}] of a suite which is executed later", test.full_name(), dependency);
+            }
+        }
+    }
+    ordered
+}
+
+/// Topological sort of the tests of a suite, of all the tests whose dependencies are already ordered
+/// the one with the lowest order goes first, then the one which comes first.
+fn order_suite<'a>(tests: &[&'a IntegrationTestMeta], known_tests: &HashSet<String>) -> Vec<&'a IntegrationTestMeta> {
+    let mut tests = tests.to_vec();
+    // stable, so the index is the priority of the test
+    tests.sort_by_key(|test| (test.order.is_none(), test.order));
+    let names: Vec<String> = tests.iter().map(|test| test.full_name()).collect();
+    let indexes: HashMap<&str, usize> = names.iter()
+        .enumerate()
+        .map(|(index, name)| (name.as_str(), index))
+        .collect();
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); tests.len()];
+    let mut pending_dependencies: Vec<usize> = vec![0; tests.len()];
+    for (index, test) in tests.iter().enumerate() {
+        for dependency in dependencies_of(test, known_tests) {
+            if let Some(&dependency) = indexes.get(dependency.as_str()) {
+                dependents[dependency].push(index);
+                pending_dependencies[index] += 1;
+            }
+        }
+    }
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..tests.len())
+        .filter(|index| pending_dependencies[*index] == 0)
+        .map(Reverse)
+        .collect();
+    let mut ordered = Vec::with_capacity(tests.len());
+    while let Some(Reverse(index)) = ready.pop() {
+        ordered.push(tests[index]);
+        for &dependent in &dependents[index] {
+            pending_dependencies[dependent] -= 1;
+            if pending_dependencies[dependent] == 0 {
+                ready.push(Reverse(dependent));
+            }
+        }
+    }
+    if ordered.len() < tests.len() {
+        let cyclic: Vec<&str> = (0..tests.len())
+            .filter(|index| pending_dependencies[*index] > 0)
+            .map(|index| names[index].as_str())
+            .collect();
+        panic!("Dependencies of the tests [{}] are cyclic", cyclic.join(", "));
+    }
+    ordered
+}
+
+pub(crate) fn skipped_test_result(test: &IntegrationTestMeta, dependency: &str) -> TestResult {
+    let name = test.full_name();
+    log_error_test(format_args!("Test [{}] SKIPPED, dependency [{}] did not pass", name, dependency));
+    TestResult {
+        name,
+        success: false,
+        duration: Duration::ZERO,
+        output: None,
+        failure: None,
+        skip_reason: Some(format!("skipped due to failed dependency [{}]", dependency)),
+        attempts: 0,
+    }
+}
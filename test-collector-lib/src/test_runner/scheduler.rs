@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use futures::future::{select, Either};
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use test_collector_utils::{Fixtures, IntegrationTestMeta, Serial};
+use crate::{TestEnvironment, TestFailure, TestResult};
+use crate::logger::{log_static_info, log_test};
+use crate::execution::{execute_async, expect_outcome, sync_test, ShouldPanic};
+use crate::fixtures::{async_test, EnvironmentFixtures};
+use crate::test_runner::{fail_by_hook, to_test_result, TestRunner};
+use crate::test_runner::ordering::{skipped_test_result, Dependencies, DependencyState};
+use crate::worker_pool::WorkerPool;
+
+impl<T: TestEnvironment + 'static> TestRunner<T> {
+    /// Sync tests are executed by the worker pool (or on the current thread if there is only one test thread),
+    /// async tests are polled together on the current thread, the one which owns the environment,
+    /// which is why all the hooks are invoked here as well.
+    /// Tests are started in their order, results are returned in the same order, not in the order of completion.
+    /// Sync tests which take fixtures are executed on the current thread, since they borrow the environment,
+    /// once the running async tests finished.
+    /// Failed tests which have attempts left are started again before the next not yet started test.
+    /// Tests wait for their dependencies to finish and are skipped if one of them did not pass.
+    /// Tests blocked by a running test of their serial group are started as soon as it finished,
+    /// the following tests are started meanwhile.
+    /// Once the fail-fast limit is reached or the run is interrupted no more tests are started, they have no result.
+    pub(super) async fn run_tests_concurrently(&self,
+                                               tests: &[&IntegrationTestMeta],
+                                               dependencies: &Dependencies,
+                                               failed_before: usize) -> Vec<Option<TestResult>> {
+        log_static_info(format_args!("Sync tests are executed on {} threads, up to {} async tests at the same time",
+                                     self.test_threads, self.async_concurrency));
+        let mut pool = if self.test_threads > 1 {
+            Some(WorkerPool::new(self.test_threads))
+        } else {
+            None
+        };
+        let fixtures = EnvironmentFixtures::new(&self.test_environment);
+        let mut async_tests = FuturesUnordered::new();
+        let mut schedule = Schedule::new(tests, failed_before);
+        loop {
+            while let Some((index, source)) = schedule.next_test(self, dependencies) {
+                let test = tests[index];
+                match (sync_test(test), pool.as_mut()) {
+                    (Some(sync_fn), Some(pool)) => {
+                        if pool.in_flight() >= pool.size() {
+                            break;
+                        }
+                        let name = test.full_name();
+                        match self.before_each(test, &name).await {
+                            Ok(_) => {
+                                pool.submit(index, name, sync_fn, self.timeout_of(test), ShouldPanic::of(test));
+                                schedule.started(index, source);
+                            }
+                            Err(failure) => schedule.not_started(index, source, to_test_result(&name, Duration::ZERO, Err(failure), None)),
+                        }
+                    }
+                    _ if !test.is_async() => {
+                        // the running async tests are not polled meanwhile, so they finish first
+                        if !async_tests.is_empty() {
+                            break;
+                        }
+                        let result = self.run_test_with_hooks(test).await;
+                        schedule.executed(self, index, source, result);
+                    }
+                    _ => {
+                        if async_tests.len() >= self.async_concurrency {
+                            break;
+                        }
+                        let name = test.full_name();
+                        match self.before_each(test, &name).await {
+                            Ok(_) => {
+                                async_tests.push(run_async_test(index, name, test, &fixtures, self.timeout_of(test)));
+                                schedule.started(index, source);
+                            }
+                            Err(failure) => schedule.not_started(index, source, to_test_result(&name, Duration::ZERO, Err(failure), None)),
+                        }
+                    }
+                }
+            }
+
+            let pool_in_flight = pool.as_ref().map(|pool| pool.in_flight()).unwrap_or(0);
+            let (index, result) = if let Some(finished) = schedule.finished.pop_front() {
+                finished
+            } else {
+                match (pool.as_mut().filter(|_| pool_in_flight > 0), async_tests.is_empty()) {
+                    (Some(pool), false) => match select(Box::pin(pool.next_result()), async_tests.next()).await {
+                        Either::Left((finished, _)) => finished,
+                        Either::Right((finished, _)) => finished.expect("Async test must be running"),
+                    },
+                    (Some(pool), true) => pool.next_result().await,
+                    (None, false) => async_tests.next().await.expect("Async test must be running"),
+                    (None, true) => break,
+                }
+            };
+            let result = schedule.attempt_finished(index, result);
+            let after_each = self.after_each(tests[index], &result).await;
+            schedule.record(self, index, result, after_each);
+        }
+        schedule.results
+    }
+
+    /// Used when async tests are executed one by one: sync tests are executed by the worker pool,
+    /// async tests and sync tests which take fixtures on the current thread, both in the same order and
+    /// with the same dependencies, serial groups and retries as `run_tests_concurrently`.
+    /// Nothing is driven by an enclosing `block_on`, so the sync hooks can call `block_on` of the environment.
+    pub(super) fn run_tests_in_parallel(&self,
+                                        tests: &[&IntegrationTestMeta],
+                                        dependencies: &Dependencies,
+                                        failed_before: usize) -> Vec<Option<TestResult>> {
+        log_static_info(format_args!("Sync tests are executed on {} threads", self.test_threads));
+        let mut pool = WorkerPool::new(self.test_threads);
+        let mut schedule = Schedule::new(tests, failed_before);
+        loop {
+            while let Some((index, source)) = schedule.next_test(self, dependencies) {
+                let test = tests[index];
+                match sync_test(test) {
+                    Some(sync_fn) => {
+                        if pool.in_flight() >= pool.size() {
+                            break;
+                        }
+                        let name = test.full_name();
+                        match self.before_each_blocking(test, &name) {
+                            Ok(_) => {
+                                pool.submit(index, name, sync_fn, self.timeout_of(test), ShouldPanic::of(test));
+                                schedule.started(index, source);
+                            }
+                            Err(failure) => schedule.not_started(index, source, to_test_result(&name, Duration::ZERO, Err(failure), None)),
+                        }
+                    }
+                    None => {
+                        let result = self.run_test_sequentially(test);
+                        schedule.executed(self, index, source, result);
+                    }
+                }
+            }
+
+            let (index, result) = match schedule.finished.pop_front() {
+                Some(finished) => finished,
+                None if pool.in_flight() > 0 => pool.next_result_blocking(),
+                None => break,
+            };
+            let result = schedule.attempt_finished(index, result);
+            let after_each = self.after_each_blocking(tests[index], &result);
+            schedule.record(self, index, result, after_each);
+        }
+        schedule.results
+    }
+}
+
+/// Progress of the tests executed concurrently, shared by both schedulers
+struct Schedule<'a> {
+    tests: &'a [&'a IntegrationTestMeta],
+    results: Vec<Option<TestResult>>,
+    attempts: Vec<u32>,
+    queue: StartQueue,
+    /// Tests which were started, removed once they finished
+    running: Vec<usize>,
+    /// Tests which were not started because `before_each_test` failed
+    finished: VecDeque<(usize, TestResult)>,
+    failures: usize,
+}
+
+impl<'a> Schedule<'a> {
+    fn new(tests: &'a [&'a IntegrationTestMeta], failed_before: usize) -> Schedule<'a> {
+        Schedule {
+            tests,
+            results: tests.iter().map(|_| None).collect(),
+            attempts: tests.iter().map(|_| 0).collect(),
+            queue: StartQueue::new(tests.len()),
+            running: Vec::new(),
+            finished: VecDeque::new(),
+            failures: failed_before,
+        }
+    }
+
+    /// Next test which can be started, tests whose dependencies did not pass are skipped on the way.
+    /// `None` if the run is stopped, all tests were started or the next test has to wait for its dependencies.
+    fn next_test<T: TestEnvironment + 'static>(&mut self, runner: &TestRunner<T>, dependencies: &Dependencies) -> Option<(usize, Source)> {
+        while !runner.run_stopped(self.failures) {
+            let (index, source) = self.queue.peek(|index| can_start(self.tests[index], &self.running, self.tests))?;
+            let test = self.tests[index];
+            match runner.dependency_state(index, dependencies) {
+                // tests are started in their order, so the following ones wait as well
+                DependencyState::Waiting => return None,
+                DependencyState::Failed(dependency) => {
+                    let result = skipped_test_result(test, &dependency);
+                    runner.record_outcome(&result);
+                    self.results[index] = Some(result);
+                    self.queue.take(source);
+                }
+                // the following tests are started meanwhile, unless they are blocked as well
+                DependencyState::Ready if !can_start(test, &self.running, self.tests) => self.queue.defer(source, index),
+                DependencyState::Ready => return Some((index, source)),
+            }
+        }
+        None
+    }
+
+    fn started(&mut self, index: usize, source: Source) {
+        self.running.push(index);
+        self.queue.take(source);
+    }
+
+    /// `after_each_test` is still invoked for the test, once its result is taken from `finished`
+    fn not_started(&mut self, index: usize, source: Source, result: TestResult) {
+        self.finished.push_back((index, result));
+        self.queue.take(source);
+    }
+
+    /// Records the final result of a test which was executed on the current thread, with its hooks and retries
+    fn executed<T: TestEnvironment + 'static>(&mut self, runner: &TestRunner<T>, index: usize, source: Source, result: TestResult) {
+        runner.record_outcome(&result);
+        if !result.success {
+            self.failures += 1;
+        }
+        self.results[index] = Some(result);
+        self.queue.take(source);
+    }
+
+    /// Counts the attempt of the finished test, before its `after_each_test` is invoked
+    fn attempt_finished(&mut self, index: usize, mut result: TestResult) -> TestResult {
+        self.running.retain(|running| *running != index);
+        self.attempts[index] += 1;
+        result.attempts = self.attempts[index];
+        result
+    }
+
+    /// Records the result once `after_each_test` finished, or queues the test again if it has attempts left
+    fn record<T: TestEnvironment + 'static>(&mut self,
+                                            runner: &TestRunner<T>,
+                                            index: usize,
+                                            mut result: TestResult,
+                                            after_each: Result<(), TestFailure>) {
+        fail_by_hook(&mut result, after_each);
+        let retry = !runner.run_stopped(self.failures) && runner.should_retry(self.tests[index], &result);
+        if !retry {
+            runner.record_outcome(&result);
+            if !result.success {
+                self.failures += 1;
+            }
+        }
+        // a pending retry keeps the last failure if the run is stopped before it is started
+        self.results[index] = Some(result);
+        if retry {
+            self.queue.retry(index);
+        }
+    }
+}
+
+async fn run_async_test(index: usize,
+                        name: String,
+                        test: &IntegrationTestMeta,
+                        fixtures: &dyn Fixtures,
+                        timeout: Option<Duration>) -> (usize, TestResult) {
+    let test_started = Instant::now();
+    log_test(format_args!("Running Test: [{}]", name));
+    let result = expect_outcome(ShouldPanic::of(test).as_ref(), execute_async(async_test(test, fixtures), timeout).await);
+    (index, to_test_result(&name, test_started.elapsed(), result, None))
+}
+
+/// Serial tests are not started while a test of the same group is running,
+/// exclusive ones only if no other test is running and no other test is started while they are running.
+fn can_start(test: &IntegrationTestMeta, running: &[usize], tests: &[&IntegrationTestMeta]) -> bool {
+    running.iter().all(|&index| match (&test.serial, &tests[index].serial) {
+        (Some(Serial::Exclusive), _) | (_, Some(Serial::Exclusive)) => false,
+        (Some(Serial::Group(group)), Some(Serial::Group(other))) => group != other,
+        _ => true,
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Source {
+    Retry,
+    Deferred(usize),
+    Next,
+}
+
+/// Order in which the tests are started: retries of failed tests, then tests which were blocked
+/// by their serial group once they can be started, then the not yet started tests.
+struct StartQueue {
+    retries: VecDeque<usize>,
+    deferred: Vec<usize>,
+    next_test: usize,
+    len: usize,
+}
+
+impl StartQueue {
+    fn new(len: usize) -> StartQueue {
+        StartQueue { retries: VecDeque::new(), deferred: Vec::new(), next_test: 0, len }
+    }
+
+    fn peek<F: Fn(usize) -> bool>(&self, can_start: F) -> Option<(usize, Source)> {
+        if let Some(&index) = self.retries.front() {
+            return Some((index, Source::Retry));
+        }
+        if let Some(position) = self.deferred.iter().position(|&index| can_start(index)) {
+            return Some((self.deferred[position], Source::Deferred(position)));
+        }
+        if self.next_test < self.len {
+            return Some((self.next_test, Source::Next));
+        }
+        None
+    }
+
+    fn take(&mut self, source: Source) {
+        match source {
+            Source::Retry => {
+                self.retries.pop_front();
+            }
+            Source::Deferred(position) => {
+                self.deferred.remove(position);
+            }
+            Source::Next => self.next_test += 1,
+        }
+    }
+
+    fn defer(&mut self, source: Source, index: usize) {
+        self.take(source);
+        self.deferred.push(index);
+    }
+
+    fn retry(&mut self, index: usize) {
+        self.retries.push_back(index);
+    }
+}
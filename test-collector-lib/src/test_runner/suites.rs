@@ -0,0 +1,113 @@
+//! Suites of tests, `#[collect_test(suite = "billing")]`, executed together between the start and stop of the suite.
+use std::collections::HashSet;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+use test_collector_utils::IntegrationTestMeta;
+use crate::{SuiteResults, TestEnvironment, TestResult};
+use crate::logger::{log_error_static_info, log_static_info};
+use crate::panic_hook::panic_message;
+use crate::test_runner::{all_tests, setup_failed_test_result, TestRunner};
+
+impl<T: TestEnvironment + 'static> TestRunner<T> {
+    /// Panics if a registered suite has no tests, it is most likely a typo in its name
+    pub(super) fn check_suites(&self, dynamic_tests: &[IntegrationTestMeta]) {
+        let mut names: Vec<&String> = self.suites.keys().collect();
+        names.sort();
+        for name in names {
+            if !all_tests(dynamic_tests).any(|test| test.suite.as_ref() == Some(name)) {
+                panic!("Suite [{}] is registered, but no test belongs to it", name);
+            }
+        }
+    }
+
+    /// Starts the suite if it is registered, runs its tests and stops it even if the start panicked.
+    /// The suite is not started if the run was already stopped, its tests have no result in this case.
+    pub(super) fn run_suite(&self,
+                            name: &str,
+                            tests: &[&IntegrationTestMeta],
+                            known_tests: &HashSet<String>,
+                            failed_before: usize) -> (Vec<Option<TestResult>>, SuiteResults) {
+        let mut suite_results = SuiteResults::new(name);
+        if self.run_stopped(failed_before) {
+            return (tests.iter().map(|_| None).collect(), suite_results);
+        }
+        let suite = self.suites.get(name);
+        log_static_info(format_args!("Starting suite [{}] with {} tests", name, tests.len()));
+        let start_started_at = Instant::now();
+        suite_results.start_error = suite.and_then(|suite| {
+            execute_suite_hook(|| suite.borrow_mut().start(&self.test_environment))
+        });
+        suite_results.start_up_duration = start_started_at.elapsed();
+
+        let tests_started_at = Instant::now();
+        let executed = match &suite_results.start_error {
+            None => self.execute(tests, known_tests, failed_before),
+            Some(error) => {
+                let message = format!("Suite [{}] failed to start: {}", name, error);
+                log_error_static_info(format_args!("{}", message));
+                tests.iter().map(|test| Some(setup_failed_test_result(test, &message))).collect()
+            }
+        };
+        suite_results.tests_duration = tests_started_at.elapsed();
+
+        let stop_started_at = Instant::now();
+        suite_results.stop_error = suite.and_then(|suite| {
+            execute_suite_hook(|| suite.borrow_mut().stop(&self.test_environment))
+        });
+        suite_results.stop_duration = stop_started_at.elapsed();
+        if let Some(error) = &suite_results.stop_error {
+            log_error_static_info(format_args!("Suite [{}] failed to stop: {}", name, error));
+        }
+        log_static_info(format_args!("Suite [{}] finished within {:?}", name,
+                                     suite_results.start_up_duration + suite_results.tests_duration + suite_results.stop_duration));
+        (executed, suite_results)
+    }
+}
+
+/// Returns the panic message if the hook panicked
+fn execute_suite_hook<F: FnOnce()>(hook: F) -> Option<String> {
+    panic::catch_unwind(AssertUnwindSafe(hook))
+        .err()
+        .map(|e| panic_message(e.as_ref()))
+}
+
+/// Tests without a suite go first, then the suites in the order of their first test.
+/// The order of the tests within a suite is kept.
+pub(super) fn group_by_suite(mut tests: Vec<&IntegrationTestMeta>) -> Vec<&IntegrationTestMeta> {
+    let mut suites: Vec<&str> = Vec::new();
+    for suite in tests.iter().filter_map(|test| test.suite.as_deref()) {
+        if !suites.contains(&suite) {
+            suites.push(suite);
+        }
+    }
+    tests.sort_by_key(|test| test.suite.as_deref().and_then(|suite| suites.iter().position(|known| *known == suite)));
+    tests
+}
+
+impl SuiteResults {
+    fn new(name: &str) -> SuiteResults {
+        SuiteResults {
+            name: name.to_string(),
+            passed: 0,
+            failed: 0,
+            flaky: 0,
+            not_run: 0,
+            start_error: None,
+            stop_error: None,
+            start_up_duration: Duration::ZERO,
+            tests_duration: Duration::ZERO,
+            stop_duration: Duration::ZERO,
+        }
+    }
+
+    pub(super) fn count(&mut self, result: Option<&TestResult>) {
+        match result {
+            None => self.not_run += 1,
+            Some(result) if result.skip_reason.is_some() => self.not_run += 1,
+            Some(result) if !result.success => self.failed += 1,
+            Some(result) if result.attempts > 1 => self.flaky += 1,
+            Some(_) => self.passed += 1,
+        }
+    }
+}
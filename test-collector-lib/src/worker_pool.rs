@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
+use test_collector_utils::SyncFn;
+use crate::logger::log_test;
+use crate::execution::{execute_sync, expect_outcome, ShouldPanic};
+use crate::test_runner::to_test_result;
+use crate::TestResult;
+
+struct Job {
+    index: usize,
+    name: String,
+    function: SyncFn,
+    timeout: Option<Duration>,
+    should_panic: Option<ShouldPanic>,
+}
+
+/// Fixed number of threads which execute sync tests.
+/// Only the test function is executed on the worker, everything related to the
+/// environment stays on the thread which owns the pool.
+pub(crate) struct WorkerPool {
+    jobs: Option<Sender<Job>>,
+    results: UnboundedReceiver<(usize, TestResult)>,
+    workers: Vec<JoinHandle<()>>,
+    in_flight: usize,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(threads: usize) -> WorkerPool {
+        let (jobs, job_receiver) = channel::<Job>();
+        let (result_sender, results) = unbounded();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let workers = (0..threads)
+            .map(|number| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                thread::Builder::new()
+                    .name(format!("test-worker-{}", number))
+                    .spawn(move || run_worker(job_receiver, result_sender))
+                    .expect("Failed to spawn test worker thread")
+            })
+            .collect();
+        WorkerPool {
+            jobs: Some(jobs),
+            results,
+            workers,
+            in_flight: 0,
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    pub(crate) fn submit(&mut self,
+                         index: usize,
+                         name: String,
+                         function: SyncFn,
+                         timeout: Option<Duration>,
+                         should_panic: Option<ShouldPanic>) {
+        self.jobs.as_ref()
+            .expect("Worker pool is already shut down")
+            .send(Job { index, name, function, timeout, should_panic })
+            .expect("All test workers are gone");
+        self.in_flight += 1;
+    }
+
+    /// Waits for any of the submitted tests to finish, returns its index and result.
+    /// Does not block the thread, so async tests can make progress in the meantime.
+    pub(crate) async fn next_result(&mut self) -> (usize, TestResult) {
+        let result = self.results.next().await.expect("All test workers are gone");
+        self.in_flight -= 1;
+        result
+    }
+
+    /// Blocks the thread until any of the submitted tests finished, used when there is no enclosing `block_on`
+    pub(crate) fn next_result_blocking(&mut self) -> (usize, TestResult) {
+        futures::executor::block_on(self.next_result())
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // closing the channel stops the workers
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(jobs: Arc<Mutex<Receiver<Job>>>, results: UnboundedSender<(usize, TestResult)>) {
+    loop {
+        let job = match jobs.lock().expect("Test job queue is poisoned").recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let test_started = Instant::now();
+        log_test(format_args!("Running Test: [{}]", job.name));
+        let result = expect_outcome(job.should_panic.as_ref(), execute_sync(job.function, job.timeout));
+        let result = to_test_result(&job.name, test_started.elapsed(), result, None);
+        if results.unbounded_send((job.index, result)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv};
+
+static FIRST_STARTED: AtomicBool = AtomicBool::new(false);
+static SECOND_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn async_tests_are_polled_concurrently() {
+    let results = runner(TestEnv)
+        .with_async_concurrency(2)
+        .run_safe();
+
+    assert_eq!(results.success_tests.len(), 2);
+    assert_eq!(results.failed_tests.len(), 1);
+    let failure = results.failed_tests[0].failure.as_ref().unwrap();
+    assert_eq!(failure.message, "panic is isolated");
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Passes only if the other test is polled at the same time
+async fn wait_for(other_test_started: &AtomicBool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !other_test_started.load(Ordering::SeqCst) {
+        assert!(Instant::now() < deadline, "Async tests were not executed concurrently");
+        YieldNow(false).await;
+    }
+}
+
+#[collect_test(async)]
+pub async fn first_concurrent_test() {
+    FIRST_STARTED.store(true, Ordering::SeqCst);
+    wait_for(&SECOND_STARTED).await;
+}
+
+#[collect_test(async)]
+pub async fn second_concurrent_test() {
+    SECOND_STARTED.store(true, Ordering::SeqCst);
+    wait_for(&FIRST_STARTED).await;
+}
+
+#[collect_test(async)]
+pub async fn panicking_concurrent_test() {
+    YieldNow(false).await;
+    panic!("panic is isolated");
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use std::error::Error;
+use std::future::Future;
+use test_collector::{FailureKind, IntegrationTestMeta, TestEnvironment, TestResult};
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{record, runner, sequential, take_calls};
+
+/// Yields once, so the hooks are really driven by the executor
+async fn seed_via_http(call: &'static str) {
+    futures_timer::Delay::new(std::time::Duration::from_millis(1)).await;
+    record(call);
+}
+
+struct AsyncHooksEnv {
+    migrations_fail: bool,
+}
+
+impl TestEnvironment for AsyncHooksEnv {
+    fn start(self) -> Self {
+        record("start");
+        self
+    }
+
+    fn before_each_test(&self, _test: &IntegrationTestMeta) {
+        record("before_each_test");
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
+        record("after_each_test");
+    }
+
+    fn stop(self) -> Self {
+        record("stop");
+        self
+    }
+
+    async fn start_async(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        seed_via_http("start_async").await;
+        if self.migrations_fail {
+            return Err("migration 42 failed".into());
+        }
+        Ok(())
+    }
+
+    async fn before_each_test_async(&self, _test: &IntegrationTestMeta) {
+        seed_via_http("before_each_test_async").await;
+    }
+
+    async fn after_each_test_async(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
+        seed_via_http("after_each_test_async").await;
+    }
+
+    async fn stop_async(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        seed_via_http("stop_async").await;
+        Ok(())
+    }
+}
+
+/// Seeds synchronously by calling its own `block_on`, like an actix `SystemRunner` based environment would
+struct BlockingHooksEnv;
+
+impl TestEnvironment for BlockingHooksEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn before_each_test(&self, _test: &IntegrationTestMeta) {
+        self.block_on(seed_via_http("before_each_test"));
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
+        self.block_on(seed_via_http("after_each_test"));
+    }
+
+    fn stop(self) -> Self {
+        self
+    }
+}
+
+/// Panics in one of its async lifecycle methods, like an `.unwrap()` of a failed migration would
+struct PanickingLifecycleEnv {
+    start_panics: bool,
+}
+
+impl TestEnvironment for PanickingLifecycleEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn stop(self) -> Self {
+        record("stop");
+        self
+    }
+
+    async fn start_async(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.start_panics {
+            panic!("migration 42 failed");
+        }
+        Ok(())
+    }
+
+    async fn stop_async(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if !self.start_panics {
+            panic!("async teardown failed");
+        }
+        Ok(())
+    }
+}
+
+fn run_and_record_calls(runner: TestRunner<AsyncHooksEnv>, test_name: &str) -> Vec<String> {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner.filter_exact(test_name).run_safe();
+    assert_eq!(results.success_tests.len(), 1);
+    take_calls()
+}
+
+const EXPECTED_CALLS: [&str; 9] = ["start", "start_async", "before_each_test", "before_each_test_async", "test",
+    "after_each_test_async", "after_each_test", "stop_async", "stop"];
+
+#[test]
+fn async_hooks_are_driven_by_block_on_of_environment() {
+    let calls = run_and_record_calls(runner(AsyncHooksEnv { migrations_fail: false }),
+                                     "async_hooks::sync_test");
+    assert_eq!(calls, EXPECTED_CALLS);
+
+    let calls = run_and_record_calls(runner(AsyncHooksEnv { migrations_fail: false }),
+                                     "async_hooks::async_test");
+    assert_eq!(calls, EXPECTED_CALLS);
+}
+
+#[test]
+fn async_hooks_are_awaited_when_executed_concurrently() {
+    let calls = run_and_record_calls(runner(AsyncHooksEnv { migrations_fail: false })
+                                         .with_test_threads(2)
+                                         .with_async_concurrency(2),
+                                     "async_hooks::async_test");
+    assert_eq!(calls, EXPECTED_CALLS);
+}
+
+#[test]
+fn sync_hooks_can_call_block_on_of_environment() {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner(BlockingHooksEnv).filter("async_hooks::").run_safe();
+
+    assert!(results.failed_tests.is_empty());
+    assert_eq!(results.success_tests.len(), 2);
+    assert_eq!(take_calls(), ["before_each_test", "test", "after_each_test", "before_each_test", "test", "after_each_test"]);
+}
+
+#[test]
+fn sync_hooks_can_call_block_on_of_environment_when_executed_in_parallel() {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner(BlockingHooksEnv)
+        .filter("async_hooks::")
+        .with_test_threads(2)
+        .run_safe();
+
+    assert!(results.failed_tests.is_empty());
+    assert_eq!(results.success_tests.len(), 2);
+    let mut calls = take_calls();
+    calls.sort();
+    assert_eq!(calls, ["after_each_test", "after_each_test", "before_each_test", "before_each_test", "test", "test"]);
+}
+
+#[test]
+fn failed_async_start_fails_tests_by_setup() {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner(AsyncHooksEnv { migrations_fail: true }).run_safe();
+
+    assert_eq!(results.start_error.as_deref(), Some("migration 42 failed"));
+    assert_eq!(results.failed_tests.len(), 2);
+    assert!(results.failed_tests.iter()
+        .all(|test| test.failure.as_ref().unwrap().kind == FailureKind::Setup));
+    assert_eq!(take_calls().last().map(String::as_str), Some("stop"));
+}
+
+#[test]
+fn panic_of_async_start_fails_tests_by_setup_and_stops_environment() {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner(PanickingLifecycleEnv { start_panics: true }).run_safe();
+
+    assert_eq!(results.start_error.as_deref(), Some("start_async panicked: migration 42 failed"));
+    assert_eq!(results.failed_tests.len(), 2);
+    assert!(results.failed_tests.iter()
+        .all(|test| test.failure.as_ref().unwrap().kind == FailureKind::Setup));
+    assert_eq!(take_calls(), ["stop"]);
+}
+
+#[test]
+fn environment_is_stopped_when_async_stop_panics() {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner(PanickingLifecycleEnv { start_panics: false }).run_safe();
+
+    assert_eq!(results.success_tests.len(), 2);
+    assert_eq!(results.stop_error.as_deref(), Some("stop_async panicked: async teardown failed"));
+    assert_eq!(take_calls(), ["test", "test", "stop"]);
+}
+
+#[collect_test]
+pub fn sync_test() {
+    record("test");
+}
+
+#[collect_test(async)]
+pub async fn async_test() {
+    record("test");
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use std::future::Future;
+use std::sync::Mutex;
+use test_collector::{IntegrationTestMeta, TestEnvironment};
+use test_collector_derive::collect_test;
+use common::runner;
+
+static CASES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub struct CasesEnv {
+    greeting: String,
+}
+
+impl TestEnvironment for CasesEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn before_each_test(&self, test: &IntegrationTestMeta) {
+        if let Some(case) = &test.case {
+            CASES.lock().unwrap().push(case.clone());
+        }
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn stop(self) -> Self {
+        self
+    }
+}
+
+#[test]
+fn every_case_is_a_separate_test() {
+    let results = runner(CasesEnv { greeting: "Hello".to_string() }).run_safe();
+
+    let mut passed: Vec<&str> = results.success_tests.iter().map(|test| test.name.as_str()).collect();
+    passed.sort();
+    assert_eq!(passed, vec![
+        "cases::addition::case_1",
+        "cases::addition::case_2",
+        "cases::endpoint_is_checked::case_1",
+        "cases::endpoint_is_checked::case_2",
+        "cases::endpoint_is_documented",
+        "cases::user_is_greeted::case_1",
+        "cases::user_is_greeted::case_2",
+    ]);
+    assert_eq!(results.failed_tests.len(), 1);
+    assert_eq!(results.failed_tests[0].name, "cases::addition::case_3");
+    assert!(CASES.lock().unwrap().contains(&"2, 2, 5".to_string()));
+}
+
+#[test]
+fn dependencies_on_cases_are_relative_to_the_module() {
+    let results = runner(CasesEnv { greeting: "Hello".to_string() })
+        .filter("cases::endpoint_is_")
+        .run_safe();
+
+    let passed: Vec<&str> = results.success_tests.iter().map(|test| test.name.as_str()).collect();
+    assert_eq!(passed.len(), 3);
+    assert_eq!(passed.last(), Some(&"cases::endpoint_is_documented"));
+}
+
+#[collect_test]
+#[case(1, 1, 2)]
+#[case(2, 3, 5)]
+#[case(2, 2, 5)]
+pub fn addition(first: u32, second: u32, expected: u32) {
+    assert_eq!(first + second, expected);
+}
+
+#[collect_test(async)]
+#[case("/health", 200)]
+#[case("/missing", 404)]
+pub async fn endpoint_is_checked(path: &str, status: u16) {
+    futures_timer::Delay::new(std::time::Duration::from_millis(1)).await;
+    assert_eq!(path == "/health", status == 200);
+}
+
+#[collect_test(depends_on = "endpoint_is_checked::case_1")]
+pub fn endpoint_is_documented() {
+    assert!(CASES.lock().unwrap().iter().any(|case| case.contains("/health")));
+}
+
+#[collect_test]
+#[case("Alice")]
+#[case("Bob")]
+pub fn user_is_greeted(name: &str, env: &CasesEnv) {
+    assert_eq!(format!("{}, {}!", env.greeting, name).len(), env.greeting.len() + name.len() + 3);
+}
@@ -0,0 +1,52 @@
+//! Environment and helpers shared by the integration tests.
+//! Every test file is its own binary, so the statics are not shared between the files.
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU32, Ordering};
+use test_collector::TestEnvironment;
+use test_collector::test_runner::TestRunner;
+
+/// How many times `TestEnv` was stopped
+pub static STOPS: AtomicU32 = AtomicU32::new(0);
+static CALLS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static SEQUENTIAL: Mutex<()> = Mutex::new(());
+
+/// Environment without any services, async tests are driven by `futures::executor::block_on`
+pub struct TestEnv;
+
+impl TestEnvironment for TestEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn stop(self) -> Self {
+        STOPS.fetch_add(1, Ordering::SeqCst);
+        self
+    }
+}
+
+/// The arguments of the test binary are meant for its own harness, so they are not applied to the runner
+pub fn runner<T: TestEnvironment>(environment: T) -> TestRunner<T> {
+    TestRunner::new(environment).with_env_args(false)
+}
+
+/// Records a call of a test or a hook, in the order of the calls
+pub fn record(call: &str) {
+    CALLS.lock().unwrap().push(call.to_string());
+}
+
+/// Returns the recorded calls and forgets them
+pub fn take_calls() -> Vec<String> {
+    std::mem::take(&mut CALLS.lock().unwrap())
+}
+
+/// Tests which record calls or count them must not run at the same time as the other tests of their file
+pub fn sequential() -> MutexGuard<'static, ()> {
+    SEQUENTIAL.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use test_collector::IntegrationTestMeta;
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{record, runner, sequential, take_calls, TestEnv};
+
+/// Stands in for the JSON files of a directory, which are only known at runtime
+fn fixture_files() -> Vec<(&'static str, u32, u32)> {
+    vec![("orders.json", 2, 4), ("refunds.json", 3, 9), ("broken.json", 1, 3)]
+}
+
+fn file_tests() -> Vec<IntegrationTestMeta> {
+    fixture_files().into_iter()
+        .map(|(file, input, expected)| {
+            IntegrationTestMeta::for_sync_closure(format!("json::{}", file), move || {
+                record(file);
+                assert_eq!(input * input, expected);
+            }).depends_on(&["dynamic::account_is_created"])
+        })
+        .collect()
+}
+
+fn async_file_test() -> IntegrationTestMeta {
+    let file = "events.json".to_string();
+    IntegrationTestMeta::for_async_closure("json::events.json".to_string(), move || {
+        let file = file.clone();
+        async move {
+            futures_timer::Delay::new(std::time::Duration::from_millis(1)).await;
+            record(&file);
+        }
+    }).with_order(1)
+}
+
+fn file_runner() -> TestRunner<TestEnv> {
+    runner(TestEnv)
+        .with_tests(file_tests())
+        .with_test(async_file_test())
+}
+
+fn sorted_names(results: &[test_collector::TestResult]) -> Vec<&str> {
+    let mut names: Vec<&str> = results.iter().map(|test| test.name.as_str()).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn dynamic_tests_run_with_collected_ones() {
+    let _sequential = sequential();
+    take_calls();
+    let results = file_runner().run_safe();
+
+    assert_eq!(sorted_names(&results.success_tests), vec![
+        "dynamic::account_is_created",
+        "json::events.json",
+        "json::orders.json",
+        "json::refunds.json",
+    ]);
+    assert_eq!(sorted_names(&results.failed_tests), vec!["json::broken.json"]);
+    assert_eq!(take_calls(), vec!["events.json", "account_is_created", "orders.json", "refunds.json", "broken.json"]);
+}
+
+#[test]
+fn dynamic_tests_are_filtered_and_run_concurrently() {
+    let _sequential = sequential();
+    take_calls();
+    let results = file_runner()
+        .filter("json::")
+        .skip("broken")
+        .with_test_threads(2)
+        .with_async_concurrency(2)
+        .run_safe();
+
+    assert_eq!(results.filtered_out, 2);
+    assert_eq!(sorted_names(&results.success_tests), vec!["json::events.json", "json::orders.json", "json::refunds.json"]);
+    assert!(results.failed_tests.is_empty());
+    assert!(!take_calls().contains(&"account_is_created".to_string()));
+}
+
+#[test]
+#[should_panic(expected = "Test [json::orders.json] is already registered")]
+fn duplicated_dynamic_test_panics() {
+    file_runner().with_tests(file_tests());
+}
+
+#[collect_test]
+pub fn account_is_created() {
+    record("account_is_created");
+}
@@ -0,0 +1,19 @@
+mod common;
+
+use test_collector::args::TestArgs;
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::TestEnv;
+
+#[test]
+fn arguments_of_the_process_are_applied_by_default() {
+    // the only test of this binary, so changing the environment of the process does not affect other tests
+    std::env::set_var("RUST_TEST_SHUFFLE_SEED", "5");
+
+    assert_eq!(TestRunner::new(TestEnv).run_safe().shuffle_seed, Some(5));
+    assert_eq!(TestRunner::new(TestEnv).with_env_args(false).run_safe().shuffle_seed, None);
+    assert_eq!(TestRunner::new(TestEnv).with_args(TestArgs::default()).run_safe().shuffle_seed, None);
+}
+
+#[collect_test]
+pub fn some_test() {}
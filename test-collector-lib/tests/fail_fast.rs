@@ -0,0 +1,85 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+use test_collector::TestResult;
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv, STOPS};
+
+fn names(results: &[TestResult]) -> Vec<&str> {
+    results.iter().map(|test| test.name.as_str()).collect()
+}
+
+#[test]
+fn remaining_tests_are_not_run_after_first_failure() {
+    let stopped_before = STOPS.load(Ordering::SeqCst);
+    let results = runner(TestEnv)
+        .with_fail_fast()
+        .run_safe();
+
+    assert_eq!(names(&results.failed_tests), vec!["fail_fast::first_failing_test"]);
+    assert!(results.success_tests.is_empty());
+    assert_eq!(names(&results.not_run_tests), vec![
+        "fail_fast::second_failing_test",
+        "fail_fast::passing_test",
+        "fail_fast::third_failing_test",
+    ]);
+    for test in &results.not_run_tests {
+        assert_eq!(test.skip_reason.as_deref(), Some("not run, tests were stopped after 1 failed tests"));
+    }
+    assert!(STOPS.load(Ordering::SeqCst) > stopped_before);
+}
+
+#[test]
+fn tests_are_stopped_after_max_failures() {
+    let results = runner(TestEnv)
+        .with_max_failures(2)
+        .run_safe();
+
+    assert_eq!(names(&results.failed_tests), vec!["fail_fast::first_failing_test", "fail_fast::second_failing_test"]);
+    assert!(results.success_tests.is_empty());
+    assert_eq!(names(&results.not_run_tests), vec!["fail_fast::passing_test", "fail_fast::third_failing_test"]);
+}
+
+#[test]
+fn running_tests_finish_when_executed_concurrently() {
+    let results = runner(TestEnv)
+        .filter_exact("fail_fast::first_failing_test")
+        .filter_exact("fail_fast::second_failing_test")
+        .filter_exact("fail_fast::passing_test")
+        .with_test_threads(2)
+        .with_fail_fast()
+        .run_safe();
+
+    // both failing tests are started before the first failure is known
+    assert_eq!(names(&results.failed_tests), vec!["fail_fast::first_failing_test", "fail_fast::second_failing_test"]);
+    assert!(results.success_tests.is_empty());
+    assert_eq!(names(&results.not_run_tests), vec!["fail_fast::passing_test"]);
+}
+
+#[test]
+fn all_tests_run_without_fail_fast() {
+    let results = runner(TestEnv).run_safe();
+
+    assert_eq!(results.failed_tests.len(), 3);
+    assert_eq!(names(&results.success_tests), vec!["fail_fast::passing_test"]);
+    assert!(results.not_run_tests.is_empty());
+}
+
+// ordered, so it is known which tests are executed before the run is stopped
+#[collect_test(order = 1)]
+pub fn first_failing_test() {
+    panic!("environment is broken");
+}
+
+#[collect_test(order = 2)]
+pub fn second_failing_test() {
+    panic!("environment is broken");
+}
+
+#[collect_test(order = 3)]
+pub fn passing_test() {}
+
+#[collect_test(order = 4)]
+pub fn third_failing_test() {
+    panic!("environment is broken");
+}
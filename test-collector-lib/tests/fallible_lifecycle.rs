@@ -0,0 +1,97 @@
+mod common;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use test_collector::{EnvironmentError, FailureKind, TestEnvironment};
+use test_collector_derive::collect_test;
+use common::runner;
+
+static CLEANED_UP: AtomicBool = AtomicBool::new(false);
+
+struct BrokenDatabaseEnv;
+
+impl TestEnvironment for BrokenDatabaseEnv {
+    fn start(self) -> Self {
+        panic!("try_start is used instead");
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn stop(self) -> Self {
+        CLEANED_UP.store(true, Ordering::SeqCst);
+        self
+    }
+
+    fn try_start(self) -> Result<Self, EnvironmentError<Self>> {
+        Err(EnvironmentError::new(self, "database did not become ready within 30s"))
+    }
+}
+
+struct NotStoppableEnv;
+
+impl TestEnvironment for NotStoppableEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn stop(self) -> Self {
+        panic!("try_stop is used instead");
+    }
+
+    fn try_stop(self) -> Result<Self, EnvironmentError<Self>> {
+        Err(EnvironmentError::new(self, "container is still running"))
+    }
+}
+
+#[test]
+fn tests_fail_by_setup_when_environment_does_not_start() {
+    let results = runner(BrokenDatabaseEnv).run_safe();
+
+    assert!(results.success_tests.is_empty());
+    assert_eq!(results.failed_tests.len(), 2);
+    for test in &results.failed_tests {
+        let failure = test.failure.as_ref().unwrap();
+        assert_eq!(failure.kind, FailureKind::Setup);
+        assert_eq!(failure.message, "Test environment failed to start: database did not become ready within 30s");
+    }
+    assert_eq!(results.start_error.as_deref(), Some("database did not become ready within 30s"));
+    assert!(CLEANED_UP.load(Ordering::SeqCst));
+
+    let xml = results.to_junit_xml("integration");
+    assert!(xml.contains(r#"tests="2" failures="0" errors="2""#));
+    assert!(xml.contains(r#"<error message="Test environment failed to start: database did not become ready within 30s" type="setup">"#));
+}
+
+#[test]
+fn stop_error_is_recorded() {
+    let results = runner(NotStoppableEnv).run_safe();
+
+    assert_eq!(results.success_tests.len(), 2);
+    assert_eq!(results.stop_error.as_deref(), Some("container is still running"));
+}
+
+#[test]
+#[should_panic(expected = "Test environment failed to stop")]
+fn run_fails_when_environment_does_not_stop() {
+    runner(NotStoppableEnv).run();
+}
+
+#[test]
+#[should_panic(expected = "Test environment failed to start")]
+fn run_fails_when_environment_does_not_start_and_no_test_is_selected() {
+    runner(BrokenDatabaseEnv)
+        .filter_exact("fallible_lifecycle::unknown_test")
+        .run();
+}
+
+#[collect_test]
+pub fn sync_test() {}
+
+#[collect_test(async)]
+pub async fn async_test() {}
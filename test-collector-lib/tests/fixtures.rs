@@ -0,0 +1,101 @@
+mod common;
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::Duration;
+use test_collector::{IntegrationTestMeta, TestEnvironment};
+use test_collector_derive::collect_test;
+use common::runner;
+
+/// Not thread safe on purpose, tests taking fixtures are executed on the thread which owns the environment
+pub struct UserRepository {
+    users: RefCell<Vec<String>>,
+}
+
+pub struct FixtureEnv {
+    base_url: String,
+    users: UserRepository,
+}
+
+impl FixtureEnv {
+    fn new() -> FixtureEnv {
+        FixtureEnv {
+            base_url: "http://localhost:9090".to_string(),
+            users: UserRepository { users: RefCell::new(Vec::new()) },
+        }
+    }
+}
+
+impl TestEnvironment for FixtureEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn stop(self) -> Self {
+        self
+    }
+
+    fn fixture(&self, fixture: TypeId) -> Option<&dyn Any> {
+        if fixture == TypeId::of::<UserRepository>() {
+            Some(&self.users)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn tests_get_environment_and_fixtures() {
+    let results = runner(FixtureEnv::new()).run_safe();
+    assert_eq!(results.success_tests.len(), 4);
+    assert!(results.failed_tests.is_empty());
+}
+
+#[test]
+fn tests_get_fixtures_when_executed_concurrently() {
+    let results = runner(FixtureEnv::new())
+        .with_test_threads(4)
+        .with_async_concurrency(2)
+        .run_safe();
+    assert_eq!(results.success_tests.len(), 4);
+    assert!(results.failed_tests.is_empty());
+}
+
+#[test]
+fn default_timeout_does_not_apply_to_sync_tests_with_fixtures() {
+    let results = runner(FixtureEnv::new())
+        .filter_exact("fixtures::environment_is_injected")
+        .with_default_timeout(Duration::from_nanos(1))
+        .run_safe();
+    assert_eq!(results.success_tests.len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Test [users] takes fixtures, so it can not have a timeout")]
+fn sync_test_with_fixtures_can_not_have_timeout() {
+    IntegrationTestMeta::for_fixture_fn("users".to_string(), |_| {})
+        .with_timeout(Duration::from_secs(1));
+}
+
+#[collect_test]
+pub fn environment_is_injected(env: &FixtureEnv) {
+    assert_eq!(env.base_url, "http://localhost:9090");
+}
+
+#[collect_test(async)]
+pub async fn fixtures_are_injected(env: &FixtureEnv, users: &UserRepository) {
+    futures_timer::Delay::new(std::time::Duration::from_millis(1)).await;
+    users.users.borrow_mut().push(format!("{}/users/1", env.base_url));
+    assert!(env.users.users.borrow().contains(&"http://localhost:9090/users/1".to_string()));
+}
+
+#[collect_test(should_panic(expected = "Test environment does not provide fixture of type u16"))]
+pub fn missing_fixture_fails_the_test(_port: &u16) {}
+
+#[collect_test]
+pub fn test_without_fixtures() {}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::future::Future;
+use std::sync::Mutex;
+use test_collector::{IntegrationTestMeta, TestEnvironment, TestResult};
+use test_collector_derive::collect_test;
+use common::runner;
+
+static SCHEMAS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static DUMPED_LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static ASYNC_RESULTS: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
+struct SchemaPerTestEnv;
+
+impl TestEnvironment for SchemaPerTestEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn before_each_test(&self, test: &IntegrationTestMeta) {
+        let schema = format!("{}_{}", test.name, test.tags.join("_"));
+        SCHEMAS.lock().unwrap().push(schema);
+        assert!(test.location.as_ref().unwrap().file.ends_with("hook_context.rs"));
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn after_each_test(&self, test: &IntegrationTestMeta, result: &TestResult) {
+        assert_eq!(test.full_name(), result.name);
+        if !result.success {
+            DUMPED_LOGS.lock().unwrap().push(result.name.clone());
+        }
+    }
+
+    fn stop(self) -> Self {
+        self
+    }
+
+    async fn after_each_test_async(&self, test: &IntegrationTestMeta, result: &TestResult) {
+        ASYNC_RESULTS.lock().unwrap().push((test.name.clone(), result.success));
+    }
+}
+
+#[test]
+fn hooks_receive_test_metadata_and_result() {
+    let results = runner(SchemaPerTestEnv).run_safe();
+    assert_eq!(results.success_tests.len(), 1);
+    assert_eq!(results.failed_tests.len(), 1);
+
+    let mut schemas = SCHEMAS.lock().unwrap().clone();
+    schemas.sort();
+    assert_eq!(schemas, vec!["creates_user_db_smoke", "fails_to_delete_user_db"]);
+    assert_eq!(*DUMPED_LOGS.lock().unwrap(), vec!["hook_context::fails_to_delete_user"]);
+    let mut async_results = ASYNC_RESULTS.lock().unwrap().clone();
+    async_results.sort();
+    assert_eq!(async_results, vec![
+        ("creates_user".to_string(), true),
+        ("fails_to_delete_user".to_string(), false),
+    ]);
+}
+
+#[collect_test(tags("db", "smoke"))]
+pub fn creates_user() {}
+
+#[collect_test(async, tags("db"))]
+pub async fn fails_to_delete_user() {
+    panic!("user was not deleted");
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use test_collector::{FailureKind, IntegrationTestMeta, TestEnvironment, TestResult};
+use test_collector_derive::collect_test;
+use common::runner;
+
+static BEFORE_EACH: AtomicU32 = AtomicU32::new(0);
+static AFTER_EACH: AtomicU32 = AtomicU32::new(0);
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+struct PanickingHooksEnv;
+
+impl TestEnvironment for PanickingHooksEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn before_each_test(&self, _test: &IntegrationTestMeta) {
+        if BEFORE_EACH.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("database is not reachable");
+        }
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
+        if AFTER_EACH.fetch_add(1, Ordering::SeqCst) == 1 {
+            panic!("failed to truncate tables");
+        }
+    }
+
+    fn stop(self) -> Self {
+        STOPPED.store(true, Ordering::SeqCst);
+        self
+    }
+}
+
+#[test]
+fn panics_of_hooks_fail_the_test_and_environment_is_stopped() {
+    let results = runner(PanickingHooksEnv).run_safe();
+
+    assert_eq!(results.success_tests.len(), 1);
+    let mut failures: Vec<(FailureKind, &str)> = results.failed_tests.iter()
+        .map(|test| test.failure.as_ref().unwrap())
+        .map(|failure| (failure.kind, failure.message.as_str()))
+        .collect();
+    failures.sort_by_key(|(_, message)| *message);
+    assert_eq!(failures, vec![
+        (FailureKind::Hook, "after_each_test panicked: failed to truncate tables"),
+        (FailureKind::Hook, "before_each_test panicked: database is not reachable"),
+    ]);
+    // after_each_test is invoked even if before_each_test panicked
+    assert_eq!(AFTER_EACH.load(Ordering::SeqCst), 3);
+    assert!(STOPPED.load(Ordering::SeqCst));
+}
+
+#[collect_test]
+pub fn first_test() {}
+
+#[collect_test]
+pub fn second_test() {}
+
+#[collect_test(async)]
+pub async fn third_test() {}
@@ -0,0 +1,65 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use test_collector::args::{RunIgnored, TestArgs};
+use test_collector_derive::collect_test;
+use common::{runner, sequential, TestEnv};
+
+static IGNORED_RUNS: AtomicU32 = AtomicU32::new(0);
+
+#[test]
+fn ignored_tests_are_reported_but_not_executed() {
+    let results = runner(TestEnv)
+        .filter("ignore::")
+        .run_safe();
+
+    assert_eq!(results.success_tests.len(), 1);
+    assert!(results.failed_tests.is_empty());
+    let mut ignored: Vec<(&str, Option<&str>)> = results.ignored_tests.iter()
+        .map(|test| (test.name.as_str(), test.skip_reason.as_deref()))
+        .collect();
+    ignored.sort();
+    assert_eq!(ignored, vec![
+        ("ignore::ignored_async_test", None),
+        ("ignore::ignored_test_with_reason", Some("payment provider sandbox is down")),
+    ]);
+}
+
+#[test]
+fn only_ignored_tests_are_executed_with_ignored_flag() {
+    let _sequential = sequential();
+    IGNORED_RUNS.store(0, Ordering::SeqCst);
+    let args = TestArgs::parse(vec!["--ignored".to_string()]).unwrap();
+    let results = runner(TestEnv)
+        .with_args(args)
+        .run_safe();
+
+    assert_eq!(results.success_tests.len(), 2);
+    assert!(results.ignored_tests.is_empty());
+    assert_eq!(results.filtered_out, 1);
+    assert_eq!(IGNORED_RUNS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn all_tests_are_executed_when_ignored_are_included() {
+    let _sequential = sequential();
+    let results = runner(TestEnv)
+        .with_run_ignored(RunIgnored::Yes)
+        .run_safe();
+
+    assert_eq!(results.success_tests.len(), 3);
+    assert!(results.ignored_tests.is_empty());
+}
+
+#[collect_test(ignore = "payment provider sandbox is down")]
+pub fn ignored_test_with_reason() {
+    IGNORED_RUNS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[collect_test(async, ignore)]
+pub async fn ignored_async_test() {
+    IGNORED_RUNS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[collect_test]
+pub fn not_ignored_test() {}
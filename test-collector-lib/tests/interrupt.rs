@@ -0,0 +1,54 @@
+// the test sends SIGTERM with `kill`
+#![cfg(unix)]
+
+mod common;
+
+use std::process::Command;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use test_collector::FailureKind;
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv, STOPS};
+
+#[test]
+fn environment_is_stopped_when_run_is_interrupted() {
+    let results = runner(TestEnv)
+        .with_interrupt_grace_period(Duration::from_millis(200))
+        .run_safe();
+
+    assert!(results.interrupted);
+    assert_eq!(STOPS.load(Ordering::SeqCst), 1);
+    let interrupted: Vec<&str> = results.failed_tests.iter()
+        .filter(|test| test.failure.as_ref().unwrap().kind == FailureKind::Interrupted)
+        .map(|test| test.name.as_str())
+        .collect();
+    assert_eq!(interrupted, vec!["interrupt::sends_sigterm_and_hangs"]);
+    for test in &results.not_run_tests {
+        assert_eq!(test.skip_reason.as_deref(), Some("not run, the run was interrupted"));
+    }
+    assert_eq!(results.success_tests.len() + results.not_run_tests.len(), 2);
+
+    let results = runner(TestEnv)
+        .filter_exact("interrupt::other_sync_test")
+        .run_safe();
+    assert!(!results.interrupted);
+    assert_eq!(results.success_tests.len(), 1);
+}
+
+// with a timeout the test runs on its own thread, so it can be abandoned after the grace period
+#[collect_test(timeout = "1m")]
+pub fn sends_sigterm_and_hangs() {
+    let status = Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .expect("Failed to send SIGTERM");
+    assert!(status.success());
+    thread::sleep(Duration::from_secs(30));
+}
+
+#[collect_test]
+pub fn other_sync_test() {}
+
+#[collect_test(async)]
+pub async fn other_async_test() {}
@@ -0,0 +1,108 @@
+mod common;
+
+use test_collector::IntegrationTestMeta;
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{record, runner, sequential, take_calls, TestEnv};
+
+/// Returns the executed tests in the order they finished
+fn check_order(runner: TestRunner<TestEnv>) -> Vec<String> {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner.run_safe();
+
+    let calls = take_calls();
+    let position = |test: &str| calls.iter().position(|call| *call == test).unwrap();
+    assert!(position("create_account") < position("place_order"));
+    assert!(position("place_order") < position("refund"));
+    assert_eq!(results.success_tests.len(), 4);
+    assert_eq!(results.failed_tests.len(), 1);
+    let mut skipped: Vec<(&str, Option<&str>)> = results.not_run_tests.iter()
+        .map(|test| (test.name.as_str(), test.skip_reason.as_deref()))
+        .collect();
+    skipped.sort();
+    assert_eq!(skipped, vec![
+        ("ordering::notification_is_sent", Some("skipped due to failed dependency [ordering::payment_provider_is_up]")),
+        ("ordering::receipt_is_sent", Some("skipped due to failed dependency [ordering::notification_is_sent]")),
+    ]);
+    calls
+}
+
+#[test]
+fn tests_run_in_order_after_their_dependencies() {
+    let calls = check_order(runner(TestEnv));
+    assert_eq!(calls, vec!["create_account", "place_order", "refund", "unordered"]);
+}
+
+#[test]
+fn tests_wait_for_their_dependencies_when_executed_concurrently() {
+    check_order(runner(TestEnv)
+        .with_test_threads(4)
+        .with_async_concurrency(2));
+}
+
+fn recorded_test(name: &'static str) -> IntegrationTestMeta {
+    IntegrationTestMeta::for_sync_closure(name.to_string(), move || record(name))
+}
+
+#[test]
+fn dependencies_keep_the_order_of_the_other_tests() {
+    let _sequential = sequential();
+    take_calls();
+    runner(TestEnv)
+        .with_test(recorded_test("checkout_receipt").with_order(1).depends_on(&["checkout_payment"]))
+        .with_test(recorded_test("checkout_cleanup"))
+        .with_test(recorded_test("checkout_payment").with_order(2))
+        .with_test(recorded_test("checkout_cart").with_order(2))
+        .filter("checkout_")
+        .run_safe();
+
+    assert_eq!(take_calls(), vec!["checkout_payment", "checkout_receipt", "checkout_cart", "checkout_cleanup"]);
+}
+
+#[test]
+#[should_panic(expected = "Dependencies of the tests [cyclic_first, cyclic_second] are cyclic")]
+fn cyclic_dependencies_panic() {
+    runner(TestEnv)
+        .with_test(recorded_test("cyclic_first").depends_on(&["cyclic_second"]))
+        .with_test(recorded_test("cyclic_second").depends_on(&["cyclic_first"]))
+        .run_safe();
+}
+
+// declared in the reverse order on purpose
+#[collect_test(order = 3, depends_on = "ordering::place_order")]
+pub fn refund() {
+    record("refund");
+}
+
+#[collect_test]
+pub fn unordered() {
+    record("unordered");
+}
+
+#[collect_test(async, order = 2, depends_on = "create_account")]
+pub async fn place_order() {
+    futures_timer::Delay::new(std::time::Duration::from_millis(10)).await;
+    record("place_order");
+}
+
+#[collect_test(order = 1)]
+pub fn create_account() {
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    record("create_account");
+}
+
+#[collect_test(depends_on("notification_is_sent"))]
+pub fn receipt_is_sent() {
+    record("receipt_is_sent");
+}
+
+#[collect_test(depends_on = "payment_provider_is_up")]
+pub fn notification_is_sent() {
+    record("notification_is_sent");
+}
+
+#[collect_test]
+pub fn payment_provider_is_up() {
+    panic!("payment provider is down");
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::future::Future;
+use std::sync::{Barrier, BarrierWaitResult};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use test_collector::{IntegrationTestMeta, TestEnvironment, TestResult};
+use test_collector_derive::collect_test;
+use common::runner;
+
+static BARRIER: Lazy<Barrier> = Lazy::new(|| Barrier::new(2));
+static BEFORE_EACH_CALLS: AtomicU32 = AtomicU32::new(0);
+static AFTER_EACH_CALLS: AtomicU32 = AtomicU32::new(0);
+
+struct ParallelEnv;
+
+impl TestEnvironment for ParallelEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn before_each_test(&self, _test: &IntegrationTestMeta) {
+        BEFORE_EACH_CALLS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
+        AFTER_EACH_CALLS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn stop(self) -> Self {
+        self
+    }
+}
+
+#[test]
+fn sync_tests_run_in_parallel() {
+    let results = runner(ParallelEnv)
+        .with_test_threads(2)
+        .run_safe();
+
+    assert!(results.failed_tests.is_empty());
+    // results are in the order of collected tests, not in the order of completion
+    let names: Vec<String> = results.success_tests.into_iter().map(|test| test.name).collect();
+    let expected: Vec<String> = inventory::iter::<IntegrationTestMeta>.into_iter()
+        .map(|test| test.full_name())
+        .collect();
+    assert_eq!(names, expected);
+    assert_eq!(BEFORE_EACH_CALLS.load(Ordering::SeqCst), 3);
+    assert_eq!(AFTER_EACH_CALLS.load(Ordering::SeqCst), 3);
+}
+
+/// Waits for the other test, so it passes only if both are running at the same time
+fn wait_for_other_test() -> BarrierWaitResult {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(BARRIER.wait());
+    });
+    receiver.recv_timeout(Duration::from_secs(10))
+        .expect("Tests were not executed in parallel")
+}
+
+#[collect_test]
+pub fn first_parallel_test() {
+    wait_for_other_test();
+}
+
+#[collect_test]
+pub fn second_parallel_test() {
+    wait_for_other_test();
+}
+
+#[collect_test(async)]
+pub async fn async_test_on_runner_thread() {
+    assert!(!thread::current().name().unwrap_or_default().starts_with("test-worker"));
+}
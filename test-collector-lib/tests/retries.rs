@@ -0,0 +1,98 @@
+mod common;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use test_collector::{IntegrationTestMeta, TestEnvironment, TestResult};
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{runner, sequential};
+
+static FLAKY_SYNC_RUNS: AtomicU32 = AtomicU32::new(0);
+static FLAKY_ASYNC_RUNS: AtomicU32 = AtomicU32::new(0);
+static BROKEN_RUNS: AtomicU32 = AtomicU32::new(0);
+static BEFORE_EACH: AtomicU32 = AtomicU32::new(0);
+static AFTER_EACH: AtomicU32 = AtomicU32::new(0);
+
+struct RetryEnv;
+
+impl TestEnvironment for RetryEnv {
+    fn start(self) -> Self {
+        self
+    }
+
+    fn before_each_test(&self, _test: &IntegrationTestMeta) {
+        BEFORE_EACH.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        futures::executor::block_on(fut)
+    }
+
+    fn after_each_test(&self, _test: &IntegrationTestMeta, _result: &TestResult) {
+        AFTER_EACH.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn stop(self) -> Self {
+        self
+    }
+}
+
+fn check_results(runner: TestRunner<RetryEnv>) {
+    let _sequential = sequential();
+    for counter in [&FLAKY_SYNC_RUNS, &FLAKY_ASYNC_RUNS, &BROKEN_RUNS, &BEFORE_EACH, &AFTER_EACH] {
+        counter.store(0, Ordering::SeqCst);
+    }
+
+    let results = runner.with_retries(1).run_safe();
+
+    let success: Vec<(&str, u32)> = results.success_tests.iter()
+        .map(|test| (test.name.as_str(), test.attempts))
+        .collect();
+    assert_eq!(success, vec![("retries::stable_test", 1)]);
+    let mut flaky: Vec<(&str, u32)> = results.flaky_tests.iter()
+        .map(|test| (test.name.as_str(), test.attempts))
+        .collect();
+    flaky.sort();
+    assert_eq!(flaky, vec![("retries::flaky_async_test", 2), ("retries::flaky_sync_test", 3)]);
+    let failed: Vec<(&str, u32)> = results.failed_tests.iter()
+        .map(|test| (test.name.as_str(), test.attempts))
+        .collect();
+    assert_eq!(failed, vec![("retries::broken_test", 2)]);
+    assert_eq!(BROKEN_RUNS.load(Ordering::SeqCst), 2);
+    // 1 + 3 + 2 + 2 attempts
+    assert_eq!(BEFORE_EACH.load(Ordering::SeqCst), 8);
+    assert_eq!(AFTER_EACH.load(Ordering::SeqCst), 8);
+}
+
+#[test]
+fn failed_tests_are_retried_and_reported_as_flaky() {
+    check_results(runner(RetryEnv));
+}
+
+#[test]
+fn failed_tests_are_retried_when_executed_concurrently() {
+    check_results(runner(RetryEnv)
+        .with_test_threads(2)
+        .with_async_concurrency(2));
+}
+
+#[collect_test]
+pub fn stable_test() {}
+
+#[collect_test(retries = 2)]
+pub fn flaky_sync_test() {
+    let run = FLAKY_SYNC_RUNS.fetch_add(1, Ordering::SeqCst) + 1;
+    assert_eq!(run, 3, "passes only on the third run");
+}
+
+#[collect_test(async)]
+pub async fn flaky_async_test() {
+    let run = FLAKY_ASYNC_RUNS.fetch_add(1, Ordering::SeqCst) + 1;
+    assert_eq!(run, 2, "passes only on the second run");
+}
+
+#[collect_test]
+pub fn broken_test() {
+    BROKEN_RUNS.fetch_add(1, Ordering::SeqCst);
+    panic!("always fails");
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::Duration;
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv};
+
+static RUNNING: AtomicU32 = AtomicU32::new(0);
+static MAX_RUNNING: AtomicU32 = AtomicU32::new(0);
+static KAFKA_RUNNING: AtomicU32 = AtomicU32::new(0);
+
+/// Returns the number of tests running at the same time, including this one
+fn enter() -> u32 {
+    let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+    MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+    running
+}
+
+fn leave() {
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn publish_to_kafka() {
+    enter();
+    assert_eq!(KAFKA_RUNNING.fetch_add(1, Ordering::SeqCst), 0, "another kafka test is running");
+    thread::sleep(Duration::from_millis(30));
+    KAFKA_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    leave();
+}
+
+fn unrelated() {
+    enter();
+    thread::sleep(Duration::from_millis(30));
+    leave();
+}
+
+#[test]
+fn serial_tests_do_not_overlap() {
+    let results = runner(TestEnv)
+        .with_test_threads(4)
+        .with_async_concurrency(4)
+        .run_safe();
+
+    assert!(results.failed_tests.is_empty());
+    assert_eq!(results.success_tests.len(), 8);
+    assert!(MAX_RUNNING.load(Ordering::SeqCst) > 1, "unrelated tests must still run in parallel");
+}
+
+#[collect_test(serial = "kafka")]
+pub fn order_event_is_published() {
+    publish_to_kafka();
+}
+
+#[collect_test(serial = "kafka")]
+pub fn refund_event_is_published() {
+    publish_to_kafka();
+}
+
+#[collect_test(async, serial = "kafka")]
+pub async fn invoice_event_is_published() {
+    enter();
+    assert_eq!(KAFKA_RUNNING.fetch_add(1, Ordering::SeqCst), 0, "another kafka test is running");
+    futures_timer::Delay::new(Duration::from_millis(30)).await;
+    KAFKA_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    leave();
+}
+
+#[collect_test(serial)]
+pub fn global_config_is_changed() {
+    assert_eq!(enter(), 1, "exclusive test must run alone");
+    thread::sleep(Duration::from_millis(30));
+    leave();
+}
+
+#[collect_test]
+pub fn first_unrelated_test() {
+    unrelated();
+}
+
+#[collect_test]
+pub fn second_unrelated_test() {
+    unrelated();
+}
+
+#[collect_test]
+pub fn third_unrelated_test() {
+    unrelated();
+}
+
+#[collect_test(async)]
+pub async fn async_unrelated_test() {
+    enter();
+    futures_timer::Delay::new(Duration::from_millis(30)).await;
+    leave();
+}
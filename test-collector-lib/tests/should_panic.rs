@@ -0,0 +1,66 @@
+mod common;
+
+use test_collector::FailureKind;
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv};
+
+fn check_results(runner: TestRunner<TestEnv>) {
+    let results = runner.run_safe();
+
+    let mut passed: Vec<&str> = results.success_tests.iter().map(|test| test.name.as_str()).collect();
+    passed.sort();
+    assert_eq!(passed, vec![
+        "should_panic::async_panicking_test",
+        "should_panic::panicking_test",
+        "should_panic::panicking_test_with_expected_message",
+    ]);
+    let mut failed: Vec<(&str, &str)> = results.failed_tests.iter()
+        .map(|test| (test.name.as_str(), test.failure.as_ref().unwrap()))
+        .map(|(name, failure)| {
+            assert_eq!(failure.kind, FailureKind::ShouldPanic);
+            (name, failure.message.as_str())
+        })
+        .collect();
+    failed.sort();
+    assert_eq!(failed, vec![
+        ("should_panic::not_panicking_test", "test did not panic as expected"),
+        ("should_panic::panicking_test_with_other_message",
+         "panic did not contain expected string\n      panic message: \"connection refused\"\n expected substring: \"insufficient funds\""),
+    ]);
+}
+
+#[test]
+fn outcome_of_should_panic_tests_is_inverted() {
+    check_results(runner(TestEnv));
+}
+
+#[test]
+fn outcome_of_should_panic_tests_is_inverted_when_executed_concurrently() {
+    check_results(runner(TestEnv)
+        .with_test_threads(2)
+        .with_async_concurrency(2));
+}
+
+#[collect_test(should_panic)]
+pub fn panicking_test() {
+    panic!("any message");
+}
+
+#[collect_test(should_panic(expected = "insufficient funds"))]
+pub fn panicking_test_with_expected_message() {
+    panic!("insufficient funds on account {}", 42);
+}
+
+#[collect_test(should_panic = "insufficient funds")]
+pub fn panicking_test_with_other_message() {
+    panic!("connection refused");
+}
+
+#[collect_test(should_panic)]
+pub fn not_panicking_test() {}
+
+#[collect_test(async, should_panic)]
+pub async fn async_panicking_test() {
+    panic!("async panic");
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{record, runner, sequential, take_calls, TestEnv};
+
+fn executed_order(runner: TestRunner<TestEnv>) -> (Vec<String>, Option<u64>) {
+    let _sequential = sequential();
+    take_calls();
+    let results = runner.run_safe();
+    assert_eq!(results.success_tests.len(), 6);
+    (take_calls(), results.shuffle_seed)
+}
+
+#[test]
+fn same_seed_gives_same_order() {
+    let (first_order, seed) = executed_order(runner(TestEnv).with_shuffle_seed(7));
+    let (second_order, _) = executed_order(runner(TestEnv).with_shuffle_seed(7));
+
+    assert_eq!(seed, Some(7));
+    assert_eq!(first_order, second_order);
+    let orders: Vec<Vec<String>> = (0..5)
+        .map(|seed| executed_order(runner(TestEnv).with_shuffle_seed(seed)).0)
+        .collect();
+    assert!(orders.iter().any(|order| *order != first_order));
+}
+
+#[test]
+fn random_seed_is_recorded() {
+    let (order, seed) = executed_order(runner(TestEnv).with_shuffle());
+
+    let (reproduced, _) = executed_order(runner(TestEnv).with_shuffle_seed(seed.unwrap()));
+    assert_eq!(order, reproduced);
+    assert_eq!(executed_order(runner(TestEnv)).1, None);
+}
+
+#[collect_test]
+pub fn first() {
+    record("first");
+}
+
+#[collect_test]
+pub fn second() {
+    record("second");
+}
+
+#[collect_test]
+pub fn third() {
+    record("third");
+}
+
+#[collect_test]
+pub fn fourth() {
+    record("fourth");
+}
+
+#[collect_test(async)]
+pub async fn fifth() {
+    record("fifth");
+}
+
+#[collect_test(async)]
+pub async fn sixth() {
+    record("sixth");
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use test_collector::{FailureKind, TestSuite};
+use test_collector_derive::collect_test;
+use common::{record, runner, take_calls, TestEnv};
+
+struct BillingSuite {
+    started: bool,
+}
+
+impl TestSuite<TestEnv> for BillingSuite {
+    fn start(&mut self, _environment: &TestEnv) {
+        self.started = true;
+        record("billing start");
+    }
+
+    fn stop(&mut self, _environment: &TestEnv) {
+        assert!(self.started);
+        record("billing stop");
+    }
+}
+
+struct BrokenSuite;
+
+impl TestSuite<TestEnv> for BrokenSuite {
+    fn start(&mut self, _environment: &TestEnv) {
+        panic!("schema could not be created");
+    }
+
+    fn stop(&mut self, _environment: &TestEnv) {
+        record("broken stop");
+    }
+}
+
+#[test]
+fn suites_are_started_and_stopped_around_their_tests() {
+    let results = runner(TestEnv)
+        .with_suite("billing", BillingSuite { started: false })
+        .with_suite("broken", BrokenSuite)
+        .run_safe();
+
+    assert_eq!(results.success_tests.len(), 4);
+    assert_eq!(results.failed_tests.len(), 1);
+    let failure = results.failed_tests[0].failure.as_ref().unwrap();
+    assert_eq!(failure.kind, FailureKind::Setup);
+    assert_eq!(failure.message, "Suite [broken] failed to start: schema could not be created");
+
+    let calls = take_calls();
+    assert_eq!(calls[0], "global test");
+    let billing_start = calls.iter().position(|call| *call == "billing start").unwrap();
+    let mut billing_calls = calls[billing_start..billing_start + 4].to_vec();
+    billing_calls[1..3].sort();
+    assert_eq!(billing_calls, vec!["billing start", "charge test", "invoice test", "billing stop"]);
+    assert!(calls.iter().any(|call| call == "broken stop"));
+    assert!(!calls.iter().any(|call| call == "broken test"));
+
+    assert_eq!(results.suites.len(), 3);
+    let suite = |name: &str| results.suites.iter().find(|suite| suite.name == name).unwrap();
+    assert_eq!(suite("billing").passed, 2);
+    assert!(suite("billing").start_error.is_none());
+    assert_eq!(suite("broken").failed, 1);
+    assert_eq!(suite("broken").start_error.as_deref(), Some("schema could not be created"));
+    assert_eq!(suite("reports").passed, 1);
+}
+
+#[test]
+#[should_panic(expected = "Suite [biling] is registered, but no test belongs to it")]
+fn suite_without_tests_panics() {
+    runner(TestEnv)
+        .with_suite("billing", BillingSuite { started: false })
+        .with_suite("biling", BillingSuite { started: false })
+        .run_safe();
+}
+
+#[collect_test]
+pub fn global_test() {
+    record("global test");
+}
+
+#[collect_test(suite = "billing")]
+pub fn invoice_test() {
+    record("invoice test");
+}
+
+#[collect_test(async, suite = "billing")]
+pub async fn charge_test() {
+    record("charge test");
+}
+
+#[collect_test(suite = "broken")]
+pub fn broken_test() {
+    record("broken test");
+}
+
+/// Suites which are not registered are still executed together and reported
+#[collect_test(suite = "reports")]
+pub fn report_test() {}
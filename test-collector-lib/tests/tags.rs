@@ -0,0 +1,53 @@
+mod common;
+
+use test_collector::args::TestArgs;
+use test_collector::test_runner::TestRunner;
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv};
+
+fn executed_tests(runner: TestRunner<TestEnv>) -> Vec<String> {
+    let results = runner.run_safe();
+    let mut names: Vec<String> = results.success_tests.into_iter().map(|test| test.name).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn run_only_tests_matching_tag_expression() {
+    let tests = executed_tests(runner(TestEnv).with_tags("smoke and not external"));
+
+    assert_eq!(tests, vec!["tags::smoke_db_test", "tags::smoke_test"]);
+}
+
+#[test]
+fn tag_expression_from_args() {
+    let args = TestArgs::parse(vec!["--tags".to_string(), "db or external".to_string()]).unwrap();
+    let tests = executed_tests(runner(TestEnv).with_args(args));
+
+    assert_eq!(tests, vec!["tags::async_external_test", "tags::smoke_db_test"]);
+}
+
+#[test]
+fn tests_without_tags_match_negations() {
+    let tests = executed_tests(runner(TestEnv).with_tags("not smoke").with_tags("not external"));
+
+    assert_eq!(tests, vec!["tags::untagged_test"]);
+}
+
+#[test]
+#[should_panic(expected = "Invalid tag expression")]
+fn invalid_tag_expression_is_rejected() {
+    let _ = runner(TestEnv).with_tags("smoke and (db");
+}
+
+#[collect_test(tags("smoke"))]
+pub fn smoke_test() {}
+
+#[collect_test(tags("smoke", "db"))]
+pub fn smoke_db_test() {}
+
+#[collect_test(async, tags("external", "smoke"))]
+pub async fn async_external_test() {}
+
+#[collect_test]
+pub fn untagged_test() {}
@@ -0,0 +1,49 @@
+mod common;
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use test_collector::FailureKind;
+use test_collector_derive::collect_test;
+use common::{runner, TestEnv, STOPS};
+
+#[test]
+fn timed_out_tests_are_reported_and_runner_continues() {
+    let results = runner(TestEnv)
+        .with_default_timeout(Duration::from_millis(200))
+        .run_safe();
+
+    let mut timed_out: Vec<&str> = results.failed_tests.iter()
+        .filter(|test| test.failure.as_ref().unwrap().kind == FailureKind::Timeout)
+        .map(|test| test.name.as_str())
+        .collect();
+    timed_out.sort();
+    assert_eq!(timed_out, vec![
+        "timeout::hanging_async_test",
+        "timeout::hanging_sync_test",
+        "timeout::slow_test_with_default_timeout",
+    ]);
+    assert_eq!(results.failed_tests.len(), 3);
+    assert_eq!(results.success_tests.len(), 1);
+    assert_eq!(STOPS.load(Ordering::SeqCst), 1);
+}
+
+#[collect_test(timeout = "100ms")]
+pub fn hanging_sync_test() {
+    thread::sleep(Duration::from_secs(30));
+}
+
+#[collect_test(async, timeout = "100ms")]
+pub async fn hanging_async_test() {
+    futures::future::pending::<()>().await;
+}
+
+#[collect_test]
+pub fn slow_test_with_default_timeout() {
+    thread::sleep(Duration::from_secs(30));
+}
+
+#[collect_test(timeout = "1m")]
+pub fn test_within_own_timeout() {
+    thread::sleep(Duration::from_millis(300));
+}
@@ -1,12 +1,74 @@
+use std::any::{type_name, Any, TypeId};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
-type AsyncFn = Box<dyn Fn() -> Pin<Box<dyn Future<Output=()>>>>;
+pub type AsyncFn = Box<dyn Fn() -> Pin<Box<dyn Future<Output=()>>>>;
+/// Sync test which is not a plain `fn()`, e.g. added at runtime with a closure capturing its data
+pub type SyncFn = Arc<dyn Fn() + Send + Sync>;
+/// Test taking the environment or its fixtures, generated by `#[collect_test]` for `fn test(env: &MyEnv)`
+pub type FixtureFn = fn(&dyn Fixtures);
+pub type AsyncFixtureFn = for<'a> fn(&'a dyn Fixtures) -> Pin<Box<dyn Future<Output=()> + 'a>>;
 
 pub struct IntegrationTestMeta{
     pub name: String,
+    pub module_path: String,
+    pub location: Option<SourceLocation>,
+    /// Overrides the default timeout of the runner
+    pub timeout: Option<Duration>,
+    /// Ignored tests are not executed unless the runner is asked to run them
+    pub ignored: bool,
+    pub ignore_reason: Option<String>,
+    /// The test passes only if it panics, with a message containing `expected_panic` if it is set
+    pub should_panic: bool,
+    pub expected_panic: Option<String>,
+    /// Categories of the test, e.g. `smoke` or `db`, used to select tests with tag expressions
+    pub tags: Vec<String>,
+    /// How many times a failed test is executed again, overrides the retries of the runner
+    pub retries: Option<u32>,
+    /// Tests of a suite are executed together, between the start and stop of the suite
+    pub suite: Option<String>,
+    /// Tests with an order are executed first, the lower the earlier
+    pub order: Option<i32>,
+    /// Names of the tests which must pass before this one, relative to the module of the test or full names
+    pub depends_on: Vec<String>,
+    /// Tests which must not be executed at the same time as other tests
+    pub serial: Option<Serial>,
+    /// Arguments of the case of a parameterized test, e.g. `"/health", 200` for `check_endpoint::case_1`
+    pub case: Option<String>,
     pub sync_fn: Option<fn()>,
+    pub sync_closure: Option<SyncFn>,
     pub async_fn: Option<AsyncFn>,
+    pub fixture_fn: Option<FixtureFn>,
+    pub async_fixture_fn: Option<AsyncFixtureFn>,
+}
+
+pub enum Serial {
+    /// `#[collect_test(serial)]`, no other test is executed at the same time
+    Exclusive,
+    /// `#[collect_test(serial = "kafka")]`, tests of the same group are not executed at the same time
+    Group(String),
+}
+
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Values which tests can take by reference, provided by the runner from the test environment
+pub trait Fixtures {
+    fn fixture(&self, fixture: TypeId) -> Option<&dyn Any>;
+}
+
+impl dyn Fixtures + '_ {
+    /// Used by `#[collect_test]`, panics (and so fails the test) if there is no fixture of the given type
+    pub fn get<F: Any>(&self) -> &F {
+        self.fixture(TypeId::of::<F>())
+            .and_then(|fixture| fixture.downcast_ref::<F>())
+            .unwrap_or_else(|| panic!("Test environment does not provide fixture of type {}", type_name::<F>()))
+    }
 }
 
 pub trait IntegrationTestRunner {
@@ -16,19 +78,153 @@ pub trait IntegrationTestRunner {
 impl IntegrationTestMeta {
     pub fn for_sync_fn(name: String, function: fn()) -> IntegrationTestMeta {
         IntegrationTestMeta {
-            name,
             sync_fn: Some(function),
-            async_fn: None
+            ..IntegrationTestMeta::new(name)
         }
     }
 
     pub fn for_async_fn(name: String, function: AsyncFn) -> IntegrationTestMeta {
+        IntegrationTestMeta {
+            async_fn: Some(function),
+            ..IntegrationTestMeta::new(name)
+        }
+    }
+
+    pub fn for_fixture_fn(name: String, function: FixtureFn) -> IntegrationTestMeta {
+        IntegrationTestMeta {
+            fixture_fn: Some(function),
+            ..IntegrationTestMeta::new(name)
+        }
+    }
+
+    pub fn for_async_fixture_fn(name: String, function: AsyncFixtureFn) -> IntegrationTestMeta {
+        IntegrationTestMeta {
+            async_fixture_fn: Some(function),
+            ..IntegrationTestMeta::new(name)
+        }
+    }
+
+    /// For tests created at runtime, the closure can be executed on another thread and several times when retried
+    pub fn for_sync_closure<F: Fn() + Send + Sync + 'static>(name: String, function: F) -> IntegrationTestMeta {
+        IntegrationTestMeta {
+            sync_closure: Some(Arc::new(function)),
+            ..IntegrationTestMeta::new(name)
+        }
+    }
+
+    /// For tests created at runtime, the closure creates a new future for every attempt
+    pub fn for_async_closure<F, Fut>(name: String, function: F) -> IntegrationTestMeta
+        where F: Fn() -> Fut + 'static,
+              Fut: Future<Output=()> + 'static {
+        IntegrationTestMeta::for_async_fn(name, Box::new(move || Box::pin(function())))
+    }
+
+    fn new(name: String) -> IntegrationTestMeta {
         IntegrationTestMeta {
             name,
+            module_path: String::new(),
+            location: None,
+            timeout: None,
+            ignored: false,
+            ignore_reason: None,
+            should_panic: false,
+            expected_panic: None,
+            tags: Vec::new(),
+            retries: None,
+            suite: None,
+            order: None,
+            depends_on: Vec::new(),
+            serial: None,
+            case: None,
             sync_fn: None,
-            async_fn: Some(function),
+            sync_closure: None,
+            async_fn: None,
+            fixture_fn: None,
+            async_fixture_fn: None,
+        }
+    }
+
+    /// Records where the test is declared, `#[collect_test]` passes `module_path!()`, `file!()`, `line!()` and `column!()`
+    pub fn with_location(mut self, module_path: &str, file: &str, line: u32, column: u32) -> IntegrationTestMeta {
+        self.module_path = module_path.to_string();
+        self.location = Some(SourceLocation {
+            file: file.to_string(),
+            line,
+            column,
+        });
+        self
+    }
+
+    /// Sync tests taking fixtures borrow the environment, so they can not be abandoned and can not have a timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> IntegrationTestMeta {
+        assert!(self.fixture_fn.is_none(), "Test [{}] takes fixtures, so it can not have a timeout", self.name);
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn ignored(mut self, reason: Option<&str>) -> IntegrationTestMeta {
+        self.ignored = true;
+        self.ignore_reason = reason.map(str::to_string);
+        self
+    }
+
+    pub fn should_panic(mut self, expected: Option<&str>) -> IntegrationTestMeta {
+        self.should_panic = true;
+        self.expected_panic = expected.map(str::to_string);
+        self
+    }
+
+    pub fn with_tags(mut self, tags: &[&str]) -> IntegrationTestMeta {
+        self.tags.extend(tags.iter().map(|tag| tag.to_string()));
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> IntegrationTestMeta {
+        self.retries = Some(retries);
+        self
+    }
+
+    pub fn in_suite(mut self, suite: &str) -> IntegrationTestMeta {
+        self.suite = Some(suite.to_string());
+        self
+    }
+
+    pub fn with_order(mut self, order: i32) -> IntegrationTestMeta {
+        self.order = Some(order);
+        self
+    }
+
+    pub fn depends_on(mut self, tests: &[&str]) -> IntegrationTestMeta {
+        self.depends_on.extend(tests.iter().map(|test| test.to_string()));
+        self
+    }
+
+    /// Without a group the test is executed alone
+    pub fn serial(mut self, group: Option<&str>) -> IntegrationTestMeta {
+        self.serial = Some(match group {
+            Some(group) => Serial::Group(group.to_string()),
+            None => Serial::Exclusive,
+        });
+        self
+    }
+
+    pub fn with_case(mut self, arguments: &str) -> IntegrationTestMeta {
+        self.case = Some(arguments.to_string());
+        self
+    }
+
+    pub fn is_async(&self) -> bool {
+        self.async_fn.is_some() || self.async_fixture_fn.is_some()
+    }
+
+    /// Name including the module path, e.g. `my_crate::users::create_user`
+    pub fn full_name(&self) -> String {
+        if self.module_path.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}::{}", self.module_path, self.name)
         }
     }
 }
 
-inventory::collect!(IntegrationTestMeta);
\ No newline at end of file
+inventory::collect!(IntegrationTestMeta);